@@ -1,22 +1,82 @@
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+use serde_derive::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
 pub enum Mode {
     #[default]
     Split,
     SideBySide,
+    TopBottom,
+    PictureInPicture,
+    Flicker,
+}
+
+/// View of the compositor saved under a number key, persisted in the bookmarks file
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Bookmark {
+    pub mode: Mode,
+    pub zoom: usize,
+    pub exact_zoom: Option<i32>,
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub border: i32,
+    pub border_y: i32,
+}
+
+impl Bookmark {
+    /// TOML table of the bookmark named `name` in the `bookmarks` table
+    pub fn toml_table(&self, name: &str) -> String {
+        let exact_zoom = self
+            .exact_zoom
+            .map(|factor| format!("exact_zoom = {factor}\n"))
+            .unwrap_or_default();
+
+        format!(
+            "[bookmarks.{name}]\nmode = \"{:?}\"\nzoom = {}\n{exact_zoom}offset_x = {}\noffset_y = {}\nborder = {}\nborder_y = {}\n",
+            self.mode, self.zoom, self.offset_x, self.offset_y, self.border, self.border_y
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Compositor {
     pub mode: Mode,
     pub zoom: usize,
+    /// Pixel-exact zoom: each pixel of the videos is shown as NxN pixels, `zoom` is ignored
+    pub exact_zoom: Option<i32>,
+    /// Nearest-neighbour scaling in the mixer (when the backend supports it)
+    pub nearest: bool,
+    /// Clamped navigation: the offsets keep the zoomed videos covering the output
+    pub clamped: bool,
     pub offset_x: i32,
     pub offset_y: i32,
     pub border: i32,
+    /// Horizontal border of the top/bottom mode
+    pub border_y: i32,
+    /// Inset of the picture_in_picture mode, its height keeps the output aspect ratio
+    pub inset_x: i32,
+    pub inset_y: i32,
+    pub inset_width: i32,
+    /// The second video is shown in flicker mode, toggled every few frames or held by a key
+    pub flicker_second: bool,
+    pub flicker_held: bool,
+    /// The second video is shown in the first position (left, top or full view) and vice versa
+    pub swapped: bool,
+    /// Loupe shown near the cursor with both videos magnified `loupe_zoom` times
+    pub loupe: bool,
+    pub loupe_x: i32,
+    pub loupe_y: i32,
+    pub loupe_zoom: i32,
+    /// Output (screen) size, where the positions are calculated
     pub width: i32,
     pub height: i32,
+    /// Size of the input videos, where the crops are calculated
+    pub video_width: i32,
+    pub video_height: i32,
+    /// Size of the decoded videos of each branch, where the crops are applied
+    pub video_sizes: [(i32, i32); 2],
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Position {
     pub xpos: i32,
     pub ypos: i32,
@@ -24,23 +84,67 @@ pub struct Position {
     pub height: i32,
     pub crop_right: i32,
     pub crop_left: i32,
+    pub crop_top: i32,
+    pub crop_bottom: i32,
 }
 
+impl Position {
+    /// Swaps the horizontal and vertical values
+    fn transpose(self) -> Self {
+        Self {
+            xpos: self.ypos,
+            ypos: self.xpos,
+            width: self.height,
+            height: self.width,
+            crop_right: self.crop_bottom,
+            crop_left: self.crop_top,
+            crop_top: self.crop_left,
+            crop_bottom: self.crop_right,
+        }
+    }
+}
+
+/// Point `(x, y)` of the output where a label is drawn
+pub type Anchor = (i32, i32);
+
 const BORDER_STEP: usize = 10;
 const WIDTH: i32 = 1280;
 const HEIGHT: i32 = 720;
 const HALF_WIDTH: i32 = WIDTH / 2;
+const HALF_HEIGHT: i32 = HEIGHT / 2;
+const INSET_MIN_WIDTH: i32 = 32;
+const LOUPE_MIN_ZOOM: i32 = 4;
+const LOUPE_MAX_ZOOM: i32 = 8;
+const LOUPE_MARGIN: i32 = 16;
+const LABEL_MARGIN: i32 = 8;
 
 impl Default for Compositor {
     fn default() -> Self {
         Self {
             mode: Mode::default(),
             zoom: 100,
+            exact_zoom: None,
+            nearest: false,
+            clamped: false,
             offset_x: 0,
             offset_y: 0,
             border: HALF_WIDTH,
+            border_y: HALF_HEIGHT,
+            inset_x: WIDTH - WIDTH / 4 - WIDTH / 40,
+            inset_y: WIDTH / 40,
+            inset_width: WIDTH / 4,
+            flicker_second: false,
+            flicker_held: false,
+            swapped: false,
+            loupe: false,
+            loupe_x: HALF_WIDTH,
+            loupe_y: HALF_HEIGHT,
+            loupe_zoom: LOUPE_MIN_ZOOM,
             width: WIDTH,
             height: HEIGHT,
+            video_width: WIDTH,
+            video_height: HEIGHT,
+            video_sizes: [(WIDTH, HEIGHT); 2],
         }
     }
 }
@@ -48,45 +152,115 @@ impl Default for Compositor {
 impl Compositor {
     #[allow(dead_code)]
     pub fn new(mode: Mode, width: i32, height: i32) -> Self {
-        Self {
+        let mut compositor = Self {
             mode,
             width,
             height,
-            border: width / 2,
             ..Default::default()
-        }
+        };
+        compositor.reset_border();
+        compositor.reset_inset();
+        compositor.set_video_size(width, height);
+        compositor
     }
 
     #[allow(dead_code)]
     pub fn new_side_by_side(width: i32, height: i32) -> Self {
-        Self {
-            mode: Mode::SideBySide,
-            width,
-            height,
-            border: width / 2,
-            ..Default::default()
-        }
+        Self::new(Mode::SideBySide, width, height)
     }
 
     #[allow(dead_code)]
     pub fn new_split(width: i32, height: i32) -> Self {
-        Self {
-            mode: Mode::Split,
-            width,
-            height,
-            border: width / 2,
-            ..Default::default()
+        Self::new(Mode::Split, width, height)
+    }
+
+    /// Returns the current view
+    pub fn get_bookmark(&self) -> Bookmark {
+        Bookmark {
+            mode: self.mode,
+            zoom: self.zoom,
+            exact_zoom: self.exact_zoom,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            border: self.border,
+            border_y: self.border_y,
+        }
+    }
+
+    /// Restores a view saved with `get_bookmark`
+    pub fn set_bookmark(&mut self, bookmark: &Bookmark) {
+        self.mode = bookmark.mode;
+        self.zoom = bookmark.zoom;
+        self.exact_zoom = bookmark.exact_zoom;
+        self.offset_x = bookmark.offset_x;
+        self.offset_y = bookmark.offset_y;
+        self.border = bookmark.border.clamp(0, self.width);
+        self.border_y = bookmark.border_y.clamp(0, self.height);
+        self.clamp_offsets();
+    }
+
+    /// Set the size of the input videos when it is not the output size
+    pub fn set_video_size(&mut self, video_width: i32, video_height: i32) {
+        self.video_width = video_width;
+        self.video_height = video_height;
+        self.video_sizes = [(video_width, video_height); 2];
+    }
+
+    /// Set the size of the decoded video of a branch (0 or 1) when it is not the input size,
+    /// as with scaled encoders w/o upscale or codecs that pad or crop
+    pub fn set_branch_video_size(&mut self, index: usize, width: i32, height: i32) {
+        self.video_sizes[index] = (width, height);
+    }
+
+    /// Crops are calculated over the input size, scaled to the decoded video of the branch
+    /// so both videos stay spatially aligned
+    fn scale_crops(&self, pos: Position, index: usize) -> Position {
+        let (width, height) = self.video_sizes[index];
+        if (width, height) == (self.video_width, self.video_height) {
+            return pos;
+        }
+
+        let scale = |crop: i32, size: i32, video_size: i32| -> i32 {
+            (crop as i64 * size as i64 / video_size.max(1) as i64) as i32
+        };
+
+        Position {
+            crop_right: scale(pos.crop_right, width, self.video_width),
+            crop_left: scale(pos.crop_left, width, self.video_width),
+            crop_top: scale(pos.crop_top, height, self.video_height),
+            crop_bottom: scale(pos.crop_bottom, height, self.video_height),
+            ..pos
         }
     }
 
     /// Set side_by_side mode
     pub fn split_mode(&mut self) {
         self.mode = Mode::Split;
+        self.clamp_offsets();
     }
 
     /// Set side_by_side mode
     pub fn side_by_side_mode(&mut self) {
         self.mode = Mode::SideBySide;
+        self.clamp_offsets();
+    }
+
+    /// Set top_bottom mode
+    pub fn top_bottom_mode(&mut self) {
+        self.mode = Mode::TopBottom;
+        self.clamp_offsets();
+    }
+
+    /// Set picture_in_picture mode
+    pub fn picture_in_picture_mode(&mut self) {
+        self.mode = Mode::PictureInPicture;
+        self.clamp_offsets();
+    }
+
+    /// Set flicker mode
+    pub fn flicker_mode(&mut self) {
+        self.mode = Mode::Flicker;
+        self.clamp_offsets();
     }
 
     /// Set side_by_side mode
@@ -101,50 +275,127 @@ impl Compositor {
         self.mode == Mode::SideBySide
     }
 
+    /// Is top_bottom mode
+    #[allow(dead_code)]
+    pub fn is_top_bottom_mode(&self) -> bool {
+        self.mode == Mode::TopBottom
+    }
+
+    /// Is picture_in_picture mode
+    pub fn is_picture_in_picture_mode(&self) -> bool {
+        self.mode == Mode::PictureInPicture
+    }
+
+    /// Is flicker mode
+    pub fn is_flicker_mode(&self) -> bool {
+        self.mode == Mode::Flicker
+    }
+
+    /// Toggles the video shown in flicker mode
+    pub fn flicker(&mut self) {
+        self.flicker_second = !self.flicker_second;
+    }
+
+    /// Shows the second video in flicker mode while held
+    pub fn hold_flicker(&mut self, held: bool) {
+        self.flicker_held = held;
+    }
+
     /// Reset default values
     pub fn reset(&mut self) {
         let d = Compositor::default();
         self.zoom = d.zoom;
+        self.exact_zoom = d.exact_zoom;
         self.offset_x = d.offset_x;
         self.offset_y = d.offset_y;
-        self.border = d.border;
+        self.reset_border();
+        self.reset_inset();
+    }
+
+    /// Reset only inset to default values: a quarter of the output in the top right corner
+    pub fn reset_inset(&mut self) {
+        self.inset_width = self.width / 4;
+        self.inset_x = self.width - self.inset_width - self.width / 40;
+        self.inset_y = self.width / 40;
     }
 
     /// Reset only border to default values
     pub fn reset_border(&mut self) {
-        let d = Compositor::default();
-        self.border = d.border;
+        self.border = self.width / 2;
+        self.border_y = self.height / 2;
     }
 
     /// Reset only border to default values
     pub fn reset_position(&mut self) {
         let d = Compositor::default();
         self.zoom = d.zoom;
+        self.exact_zoom = d.exact_zoom;
         self.offset_x = d.offset_x;
         self.offset_y = d.offset_y;
     }
 
     /// Moves the viewport by `x_step` pixels horizontally and `y_step` pixels vertically.
-    /// Does not clamp the values, allowing offsets to exceed valid bounds.
+    /// Does not clamp the values (unless clamped navigation), allowing offsets to exceed valid bounds.
     pub fn move_pos(&mut self, x_step: i32, y_step: i32) {
         self.offset_x += x_step;
         self.offset_y += y_step;
+        self.clamp_offsets();
     }
 
     /// Set offset_x and offset_y
     pub fn move_pos_to(&mut self, x: i32, y: i32) {
         self.offset_x = x;
         self.offset_y = y;
+        self.clamp_offsets();
+    }
+
+    /// Toggles the clamped navigation
+    pub fn toggle_clamped(&mut self) {
+        self.clamped = !self.clamped;
+        self.clamp_offsets();
+    }
+
+    /// In clamped navigation, keeps the zoomed videos covering the output (or inside it when
+    /// they are smaller) so panning never leaves the videos.
+    fn clamp_offsets(&mut self) {
+        if !self.clamped {
+            return;
+        }
+
+        let (viewport_width, viewport_height) = self.get_viewport_size();
+        let (max_x, max_y) = if self.is_side_by_side_mode() {
+            // each video is half of the viewport in half of the output
+            (
+                (viewport_width - self.width).abs() / 4,
+                (viewport_height / 2 - self.height).abs() / 2,
+            )
+        } else {
+            (
+                (viewport_width - self.width).abs() / 2,
+                (viewport_height - self.height).abs() / 2,
+            )
+        };
+
+        self.offset_x = self.offset_x.clamp(-max_x, max_x);
+        self.offset_y = self.offset_y.clamp(-max_y, max_y);
     }
 
     /// Offsets the border inside the bounds.
+    /// The border is horizontal (y coordinate) in top_bottom mode.
     pub fn move_border(&mut self, offset: i32) {
-        self.move_border_to(self.border + offset);
+        if self.is_top_bottom_mode() {
+            self.move_border_to(self.border_y + offset);
+        } else {
+            self.move_border_to(self.border + offset);
+        }
     }
 
     /// Set border position inside the bounds.
+    /// The border is horizontal (y coordinate) in top_bottom mode.
     pub fn move_border_to(&mut self, new_border: i32) {
-        if new_border < 0 {
+        if self.is_top_bottom_mode() {
+            self.border_y = new_border.clamp(0, self.height);
+        } else if new_border < 0 {
             self.border = 0
         } else if new_border > self.width {
             self.border = self.width
@@ -153,35 +404,223 @@ impl Compositor {
         }
     }
 
+    /// Height of the inset, with the aspect ratio of the output
+    pub fn get_inset_height(&self) -> i32 {
+        self.inset_width * self.height / self.width
+    }
+
+    /// Whether the point is inside the inset of the picture_in_picture mode
+    pub fn is_inside_inset(&self, x: i32, y: i32) -> bool {
+        self.is_picture_in_picture_mode()
+            && (self.inset_x..self.inset_x + self.inset_width).contains(&x)
+            && (self.inset_y..self.inset_y + self.get_inset_height()).contains(&y)
+    }
+
+    /// Set the inset position inside the bounds.
+    pub fn move_inset_to(&mut self, x: i32, y: i32) {
+        self.inset_x = x.clamp(0, self.width - self.inset_width);
+        self.inset_y = y.clamp(0, self.height - self.get_inset_height());
+    }
+
+    /// Resizes the inset by `step` pixels of width, keeping it inside the bounds.
+    pub fn resize_inset(&mut self, step: i32) {
+        self.inset_width = (self.inset_width + step).clamp(INSET_MIN_WIDTH, self.width);
+        self.move_inset_to(self.inset_x, self.inset_y);
+    }
+
+    /// Swaps the positions of the videos
+    pub fn swap(&mut self) {
+        self.swapped = !self.swapped;
+    }
+
+    /// Orders the `Position`s of the first and second places as the videos (mixer pads)
+    fn place(&self, first: Position, second: Position) -> (Position, Position) {
+        if self.swapped {
+            (second, first)
+        } else {
+            (first, second)
+        }
+    }
+
+    /// Shows or hides the loupe
+    pub fn toggle_loupe(&mut self) {
+        self.loupe = !self.loupe;
+    }
+
+    /// Set the point magnified by the loupe (the cursor)
+    pub fn move_loupe_to(&mut self, x: i32, y: i32) {
+        self.loupe_x = x;
+        self.loupe_y = y;
+    }
+
+    /// Increases the loupe magnification, capping it at 8x
+    pub fn loupe_zoom_in(&mut self) {
+        self.loupe_zoom = (self.loupe_zoom + 1).min(LOUPE_MAX_ZOOM);
+    }
+
+    /// Decreases the loupe magnification, ensuring it stays at a minimum of 4x
+    pub fn loupe_zoom_out(&mut self) {
+        self.loupe_zoom = (self.loupe_zoom - 1).max(LOUPE_MIN_ZOOM);
+    }
+
+    /// Square window of the loupe near the cursor, moved to the other side of the
+    /// cursor when it does not fit in the output.
+    /// Returns `(x, y, size)`
+    pub fn get_loupe_window(&self) -> (i32, i32, i32) {
+        let size = self.width.min(self.height) / 3;
+
+        let x = if self.loupe_x + LOUPE_MARGIN + size > self.width {
+            self.loupe_x - LOUPE_MARGIN - size
+        } else {
+            self.loupe_x + LOUPE_MARGIN
+        };
+        let y = if self.loupe_y + LOUPE_MARGIN + size > self.height {
+            self.loupe_y - LOUPE_MARGIN - size
+        } else {
+            self.loupe_y + LOUPE_MARGIN
+        };
+
+        (x, y, size)
+    }
+
+    /// Point of the videos under the output point `(x, y)`, as a fraction of the video size
+    fn get_video_point(&self, x: i32, y: i32) -> (f32, f32) {
+        let (viewport_width, viewport_height) = self.get_viewport_size();
+
+        let (xpos, ypos, x, width, height) = if self.is_side_by_side_mode() {
+            let pos_height = viewport_height / 2;
+            (
+                self.offset_x + (self.width - viewport_width) / 4,
+                self.offset_y + (self.height - pos_height) / 2,
+                x % (self.width / 2),
+                viewport_width / 2,
+                pos_height,
+            )
+        } else {
+            (
+                self.offset_x - (viewport_width - self.width) / 2,
+                self.offset_y - (viewport_height - self.height) / 2,
+                x,
+                viewport_width,
+                viewport_height,
+            )
+        };
+
+        (
+            (x - xpos) as f32 / width.max(1) as f32,
+            (y - ypos) as f32 / height.max(1) as f32,
+        )
+    }
+
+    /// Calculates the two `Position`s of the loupe: the magnified videos centered in the
+    /// point under the cursor, the first one in the left half of the loupe window and the
+    /// second one in the right half. Width 0 when the loupe is hidden.
+    pub fn get_loupe_positions(&self) -> (Position, Position) {
+        if !self.loupe {
+            return (Position::default(), Position::default());
+        }
+
+        let (x, y, size) = self.get_loupe_window();
+        let (video_x, video_y) = self.get_video_point(self.loupe_x, self.loupe_y);
+
+        let width = self.width * self.loupe_zoom;
+        let height = self.height * self.loupe_zoom;
+        let magnified = Position {
+            xpos: x + size / 2 - (video_x * width as f32) as i32,
+            ypos: y + size / 2 - (video_y * height as f32) as i32,
+            width,
+            height,
+            ..Position::default()
+        };
+
+        let half_size = size / 2;
+        let (pos2, pos3) = self.place(
+            self.clip(magnified, (x, y, half_size, size)),
+            self.clip(magnified, (x + half_size, y, size - half_size, size)),
+        );
+
+        (self.scale_crops(pos2, 0), self.scale_crops(pos3, 1))
+    }
+
     /// Increases the zoom level, capping it at a sensible maximum (e.g., 1000000)
     pub fn zoom_in(&mut self) {
-        let scale = if self.is_split_mode() { 2 } else { 4 };
+        let scale = if self.is_side_by_side_mode() { 4 } else { 2 };
         self.zoom_in_center_at(self.width / scale, self.height / 2);
     }
 
     /// Decreases the zoom level, ensuring it stays at a minimum of 1
     pub fn zoom_out(&mut self) {
-        let scale = if self.is_split_mode() { 2 } else { 4 };
+        let scale = if self.is_side_by_side_mode() { 4 } else { 2 };
         self.zoom_out_center_at(self.width / scale, self.height / 2);
     }
 
     /// Increases the zoom level, capping it at a sensible maximum (e.g., 1000000)
     /// Update offset to keep centered
     pub fn zoom_in_center_at(&mut self, x: i32, y: i32) {
+        self.leave_exact_zoom();
         self.zoom = (self.zoom + BORDER_STEP).min(1000000);
         self.fix_offset_when_zoom(x, y, true);
+        self.clamp_offsets();
     }
 
     /// Decreases the zoom level, ensuring it stays at a minimum of 1,
     /// Update offset to keep centered
     pub fn zoom_out_center_at(&mut self, x: i32, y: i32) {
+        self.leave_exact_zoom();
         self.zoom = (self.zoom.saturating_sub(BORDER_STEP)).max(1);
         self.fix_offset_when_zoom(x, y, false);
+        self.clamp_offsets();
+    }
+
+    /// Set the pixel-exact zoom, 1 to show the videos 1:1
+    pub fn set_exact_zoom(&mut self, factor: i32) {
+        self.exact_zoom = Some(factor.max(1));
+        self.clamp_offsets();
+    }
+
+    /// Toggles the nearest-neighbour scaling
+    pub fn toggle_nearest(&mut self) {
+        self.nearest = !self.nearest;
+    }
+
+    /// Replaces the exact zoom by the equivalent zoom level, so the zoom steps continue from it
+    fn leave_exact_zoom(&mut self) {
+        if self.exact_zoom.is_some() {
+            let (viewport_width, _) = self.get_viewport_size();
+            self.zoom = (viewport_width * 100 / self.width).max(1) as usize;
+            self.exact_zoom = None;
+        }
+    }
+
+    /// Size of the zoomed videos (twice in side_by_side mode where each one is half of it)
+    fn get_viewport_size(&self) -> (i32, i32) {
+        if let Some(factor) = self.exact_zoom {
+            let scale = if self.is_side_by_side_mode() { 2 } else { 1 };
+            return (
+                self.video_width * factor * scale,
+                self.video_height * factor * scale,
+            );
+        }
+
+        let zoom_factor = (self.zoom as f32) / 100.0;
+        (
+            (self.width as f32 * zoom_factor) as i32,
+            (self.height as f32 * zoom_factor) as i32,
+        )
+    }
+
+    /// Moves `pos` to the pixel grid of the exact zoom, where `reference` is on the grid, so
+    /// the crops are whole video pixels
+    fn align_to_pixel_grid(&self, pos: i32, reference: i32) -> i32 {
+        match self.exact_zoom {
+            Some(factor) => pos + (reference - pos).rem_euclid(factor),
+            None => pos,
+        }
     }
 
     fn fix_offset_when_zoom(&mut self, x: i32, y: i32, inside: bool) {
         match self.mode {
-            Mode::Split => {
+            Mode::Split | Mode::TopBottom | Mode::PictureInPicture | Mode::Flicker => {
                 self.fix_offset_when_zoom_split(x, y, inside);
             }
             Mode::SideBySide => {
@@ -229,17 +668,215 @@ impl Compositor {
 
     /// Calculates the two `Position`s for the input videos based on the compositor values
     pub fn get_positions(&self) -> (Position, Position) {
-        match self.mode {
+        let (first, second) = match self.mode {
             Mode::Split => self.get_positions_split(),
             Mode::SideBySide => self.get_positions_side_by_side(),
+            Mode::TopBottom => self.get_positions_top_bottom(),
+            Mode::PictureInPicture => self.get_positions_picture_in_picture(),
+            Mode::Flicker => self.get_positions_flicker(),
+        };
+        let (pos0, pos1) = self.place(first, second);
+
+        (self.scale_crops(pos0, 0), self.scale_crops(pos1, 1))
+    }
+
+    /// Only one video fills the view, the other one is hidden with width 0
+    fn get_positions_flicker(&self) -> (Position, Position) {
+        let (viewport_width, viewport_height) = self.get_viewport_size();
+
+        let shown = Position {
+            xpos: self.offset_x - (viewport_width - self.width) / 2,
+            ypos: self.offset_y - (viewport_height - self.height) / 2,
+            width: viewport_width,
+            height: viewport_height,
+            crop_right: 0,
+            crop_left: 0,
+            crop_top: 0,
+            crop_bottom: 0,
+        };
+        let hidden = Position { width: 0, ..shown };
+
+        if self.flicker_second || self.flicker_held {
+            (hidden, shown)
+        } else {
+            (shown, hidden)
+        }
+    }
+
+    /// The first video fills the view, the second one is the same view scaled down to the
+    /// inset and cropped to its bounds.
+    fn get_positions_picture_in_picture(&self) -> (Position, Position) {
+        let (viewport_width, viewport_height) = self.get_viewport_size();
+        let viewport_offset_x = self.offset_x - (viewport_width - self.width) / 2;
+        let viewport_offset_y = self.offset_y - (viewport_height - self.height) / 2;
+
+        let pos0 = Position {
+            xpos: viewport_offset_x,
+            ypos: viewport_offset_y,
+            width: viewport_width,
+            height: viewport_height,
+            crop_right: 0,
+            crop_left: 0,
+            crop_top: 0,
+            crop_bottom: 0,
+        };
+
+        let inset_scale = self.inset_width as f32 / self.width as f32;
+        let scale = |v: i32| -> i32 { (v as f32 * inset_scale) as i32 };
+        let inset_height = self.get_inset_height();
+
+        let inset = Position {
+            xpos: self.inset_x + scale(viewport_offset_x),
+            ypos: self.inset_y + scale(viewport_offset_y),
+            width: scale(viewport_width),
+            height: scale(viewport_height),
+            ..Position::default()
+        };
+        let pos1 = self.clip(
+            inset,
+            (self.inset_x, self.inset_y, self.inset_width, inset_height),
+        );
+
+        (pos0, pos1)
+    }
+
+    /// Anchors `(x, y)` of the labels of the first and second positions, whichever video is shown
+    /// there: the top right corner of the first one and the top left corner of the second one,
+    /// next to the border. `None` when the video is hidden.
+    pub fn get_label_positions(&self) -> (Option<Anchor>, Option<Anchor>) {
+        let (pos0, pos1) = self.get_positions();
+        let (pos0, pos1) = self.place(pos0, pos1);
+        let half_width = self.width / 2;
+
+        // (region of the output where the video is shown, anchor of its label)
+        let (label0, label1) = match self.mode {
+            Mode::Split => (
+                (
+                    (0, 0, self.border, self.height),
+                    (self.border - LABEL_MARGIN, LABEL_MARGIN),
+                ),
+                (
+                    (self.border, 0, self.width, self.height),
+                    (self.border + LABEL_MARGIN, LABEL_MARGIN),
+                ),
+            ),
+            Mode::SideBySide => (
+                (
+                    (0, 0, half_width, self.height),
+                    (half_width - LABEL_MARGIN, LABEL_MARGIN),
+                ),
+                (
+                    (half_width, 0, self.width, self.height),
+                    (half_width + LABEL_MARGIN, LABEL_MARGIN),
+                ),
+            ),
+            Mode::TopBottom => (
+                (
+                    (0, 0, self.width, self.border_y),
+                    (self.width - LABEL_MARGIN, LABEL_MARGIN),
+                ),
+                (
+                    (0, self.border_y, self.width, self.height),
+                    (LABEL_MARGIN, self.border_y + LABEL_MARGIN),
+                ),
+            ),
+            Mode::PictureInPicture => (
+                (
+                    (0, 0, self.width, self.height),
+                    (self.inset_x - LABEL_MARGIN, self.inset_y + LABEL_MARGIN),
+                ),
+                (
+                    (
+                        self.inset_x,
+                        self.inset_y,
+                        self.inset_x + self.inset_width,
+                        self.inset_y + self.get_inset_height(),
+                    ),
+                    (self.inset_x + LABEL_MARGIN, self.inset_y + LABEL_MARGIN),
+                ),
+            ),
+            Mode::Flicker => (
+                (
+                    (0, 0, self.width, self.height),
+                    (self.width - LABEL_MARGIN, LABEL_MARGIN),
+                ),
+                (
+                    (0, 0, self.width, self.height),
+                    (LABEL_MARGIN, LABEL_MARGIN),
+                ),
+            ),
+        };
+
+        // the video is visible when it overlaps its region
+        let visible = |pos: &Position, (x0, y0, x1, y1): (i32, i32, i32, i32)| -> bool {
+            pos.width > 0
+                && pos.height > 0
+                && x0 < x1
+                && y0 < y1
+                && pos.xpos < x1
+                && pos.xpos + pos.width > x0
+                && pos.ypos < y1
+                && pos.ypos + pos.height > y0
+        };
+
+        (
+            visible(&pos0, label0.0).then_some(label0.1),
+            visible(&pos1, label1.0).then_some(label1.1),
+        )
+    }
+
+    /// Crops the uncropped `pos` to the `(x, y, width, height)` bounds
+    fn clip(&self, pos: Position, bounds: (i32, i32, i32, i32)) -> Position {
+        let (x, y, width, height) = bounds;
+
+        let crop_left = (x - pos.xpos).clamp(0, pos.width);
+        let crop_right = (pos.xpos + pos.width - x - width).clamp(0, pos.width - crop_left);
+        let crop_top = (y - pos.ypos).clamp(0, pos.height);
+        let crop_bottom = (pos.ypos + pos.height - y - height).clamp(0, pos.height - crop_top);
+
+        // Note crop before zoom scaling (because glvideomixer implementation)
+        let unscaling = |crop: i32, size: i32, video_size: i32| -> i32 {
+            if size == 0 {
+                0
+            } else {
+                (crop as f32 * video_size as f32 / size as f32) as i32
+            }
+        };
+
+        Position {
+            xpos: pos.xpos + crop_left,
+            ypos: pos.ypos + crop_top,
+            width: pos.width - crop_left - crop_right,
+            height: pos.height - crop_top - crop_bottom,
+            crop_right: unscaling(crop_right, pos.width, self.video_width),
+            crop_left: unscaling(crop_left, pos.width, self.video_width),
+            crop_top: unscaling(crop_top, pos.height, self.video_height),
+            crop_bottom: unscaling(crop_bottom, pos.height, self.video_height),
         }
     }
 
+    /// Same as split mode with the axes swapped
+    fn get_positions_top_bottom(&self) -> (Position, Position) {
+        let transposed = Compositor {
+            mode: Mode::Split,
+            offset_x: self.offset_y,
+            offset_y: self.offset_x,
+            border: self.border_y,
+            width: self.height,
+            height: self.width,
+            video_width: self.video_height,
+            video_height: self.video_width,
+            video_sizes: [(self.video_height, self.video_width); 2],
+            ..*self
+        };
+        let (pos0, pos1) = transposed.get_positions_split();
+
+        (pos0.transpose(), pos1.transpose())
+    }
+
     //here impl
     fn get_positions_side_by_side(&self) -> (Position, Position) {
-        let zoom_factor = (self.zoom as f32) / 100.0;
-        let viewport_width = (self.width as f32 * zoom_factor) as i32;
-        let viewport_height = (self.height as f32 * zoom_factor) as i32;
+        let (viewport_width, viewport_height) = self.get_viewport_size();
 
         let half_width = self.width / 2;
         let half_viewport_width = viewport_width / 2;
@@ -247,12 +884,13 @@ impl Compositor {
         let pos_height = viewport_height / 2;
         let pos_ypos = self.offset_y + (self.height - pos_height) / 2;
 
-        let pos_xpos = self.offset_x + (self.width - viewport_width) / 4;
+        let pos_xpos =
+            self.align_to_pixel_grid(self.offset_x + (self.width - viewport_width) / 4, 0);
 
         let unscaling = |w: i32| -> i32 {
             // crop is done over the original image
-            let u_w = w * self.width / half_viewport_width;
-            if u_w < self.width {
+            let u_w = w * self.video_width / half_viewport_width;
+            if u_w < self.video_width {
                 u_w
             } else {
                 0
@@ -279,6 +917,8 @@ impl Compositor {
                 0
             },
             crop_left: 0,
+            crop_top: 0,
+            crop_bottom: 0,
         };
 
         let pos1 = Position {
@@ -305,16 +945,19 @@ impl Compositor {
                 let crop = -pos_xpos;
                 unscaling(crop)
             },
+            crop_top: 0,
+            crop_bottom: 0,
         };
 
         (pos0, pos1)
     }
 
     fn get_positions_split(&self) -> (Position, Position) {
-        let zoom_factor = (self.zoom as f32) / 100.0;
-        let viewport_width = (self.width as f32 * zoom_factor) as i32;
-        let viewport_height = (self.height as f32 * zoom_factor) as i32;
-        let viewport_offset_x = self.offset_x - (viewport_width - self.width) / 2;
+        let (viewport_width, viewport_height) = self.get_viewport_size();
+        let viewport_offset_x = self.align_to_pixel_grid(
+            self.offset_x - (viewport_width - self.width) / 2,
+            self.border,
+        );
         let viewport_offset_y = self.offset_y - (viewport_height - self.height) / 2;
 
         let pos0 = Position {
@@ -340,16 +983,18 @@ impl Compositor {
                 if viewport_width + viewport_offset_x < self.border {
                     0
                 } else if viewport_offset_x > self.border {
-                    self.width
+                    self.video_width
                 } else {
                     // Note crop before zoom scaling (because glvideomixer implementation)w
-                    let scale = self.width as f32 / viewport_width as f32;
+                    let scale = self.video_width as f32 / viewport_width as f32;
                     let crop_right_scaled =
                         (viewport_width + viewport_offset_x - self.border) as f32;
                     (crop_right_scaled * scale) as i32
                 }
             },
             crop_left: 0,
+            crop_top: 0,
+            crop_bottom: 0,
         };
 
         let pos1 = Position {
@@ -381,16 +1026,18 @@ impl Compositor {
             crop_right: 0,
             crop_left: {
                 if viewport_width + viewport_offset_x < self.border {
-                    self.width
+                    self.video_width
                 } else if viewport_offset_x > self.border {
                     0
                 } else {
                     // Note crop before zoom scaling (because glvideomixer implementation)w
-                    let scale = self.width as f32 / viewport_width as f32;
+                    let scale = self.video_width as f32 / viewport_width as f32;
                     let crop_right_scaled = (self.border - viewport_offset_x) as f32;
                     (crop_right_scaled * scale) as i32
                 }
             },
+            crop_top: 0,
+            crop_bottom: 0,
         };
 
         (pos0, pos1)
@@ -399,8 +1046,6 @@ impl Compositor {
 
 #[cfg(test)]
 mod tests {
-    const HALF_HEIGHT: i32 = HEIGHT / 2;
-
     use super::*;
 
     #[test]
@@ -1371,6 +2016,49 @@ mod tests {
         assert_eq!(compositor.height, height, "compositor.height");
     }
 
+    #[test]
+    fn test_split_output_bigger_than_video() {
+        let mut compositor = Compositor::new_split(2 * WIDTH, 2 * HEIGHT);
+        compositor.set_video_size(WIDTH, HEIGHT);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(pos0.xpos, 0, "pos0.xpos");
+        assert_eq!(pos0.width, WIDTH, "pos0.width");
+        assert_eq!(pos0.height, 2 * HEIGHT, "pos0.height");
+        assert_eq!(pos0.crop_right, HALF_WIDTH, "pos0.crop_right");
+
+        assert_eq!(pos1.xpos, WIDTH, "pos1.xpos");
+        assert_eq!(pos1.width, WIDTH, "pos1.width");
+        assert_eq!(pos1.height, 2 * HEIGHT, "pos1.height");
+        assert_eq!(pos1.crop_left, HALF_WIDTH, "pos1.crop_left");
+
+        compositor.move_border_to(0);
+        let (pos0, pos1) = compositor.get_positions();
+        assert_eq!(pos0.width, 0, "pos0.width");
+        assert_eq!(pos0.crop_right, WIDTH, "pos0.crop_right");
+        assert_eq!(pos1.width, 2 * WIDTH, "pos1.width");
+        assert_eq!(pos1.crop_left, 0, "pos1.crop_left");
+
+        compositor.reset();
+        assert_eq!(compositor.border, WIDTH, "compositor.border");
+    }
+
+    #[test]
+    fn test_sidebyside_output_smaller_than_video() {
+        let mut compositor = Compositor::new_side_by_side(HALF_WIDTH, HALF_HEIGHT);
+        compositor.set_video_size(WIDTH, HEIGHT);
+        compositor.move_pos(-HALF_WIDTH / 4, 0);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(pos0.xpos, -HALF_WIDTH / 4, "pos0.xpos");
+        assert_eq!(pos0.width, HALF_WIDTH / 2, "pos0.width");
+        assert_eq!(pos0.crop_right, 0, "pos0.crop_right");
+
+        assert_eq!(pos1.xpos, HALF_WIDTH / 2, "pos1.xpos");
+        assert_eq!(pos1.width, HALF_WIDTH / 4, "pos1.width");
+        assert_eq!(pos1.crop_left, HALF_WIDTH, "pos1.crop_left");
+    }
+
     #[test]
     fn test_sidebyside_bug_1() {
         let mut compositor = Compositor {
@@ -1426,4 +2114,540 @@ mod tests {
         assert_eq!(pos1.crop_right, 0, "pos1.crop_right");
         assert_eq!(pos1.crop_left, 224, "pos1.crop_left");
     }
+
+    #[test]
+    fn test_topbottom_get_positions_default() {
+        let compositor = Compositor::new(Mode::TopBottom, WIDTH, HEIGHT);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(compositor.border_y, HALF_HEIGHT, "compositor.border_y");
+
+        assert_eq!(pos0.xpos, 0, "pos0.xpos");
+        assert_eq!(pos0.ypos, 0, "pos0.ypos");
+        assert_eq!(pos0.width, WIDTH, "pos0.width");
+        assert_eq!(pos0.height, HALF_HEIGHT, "pos0.height");
+        assert_eq!(pos0.crop_right, 0, "pos0.crop_right");
+        assert_eq!(pos0.crop_left, 0, "pos0.crop_left");
+        assert_eq!(pos0.crop_top, 0, "pos0.crop_top");
+        assert_eq!(pos0.crop_bottom, HALF_HEIGHT, "pos0.crop_bottom");
+
+        assert_eq!(pos1.xpos, 0, "pos1.xpos");
+        assert_eq!(pos1.ypos, HALF_HEIGHT, "pos1.ypos");
+        assert_eq!(pos1.width, WIDTH, "pos1.width");
+        assert_eq!(pos1.height, HALF_HEIGHT, "pos1.height");
+        assert_eq!(pos1.crop_right, 0, "pos1.crop_right");
+        assert_eq!(pos1.crop_left, 0, "pos1.crop_left");
+        assert_eq!(pos1.crop_top, HALF_HEIGHT, "pos1.crop_top");
+        assert_eq!(pos1.crop_bottom, 0, "pos1.crop_bottom");
+    }
+
+    #[test]
+    fn test_topbottom_move_border_and_pos() {
+        let mut compositor = Compositor::new(Mode::TopBottom, WIDTH, HEIGHT);
+        compositor.move_border(10);
+        compositor.move_pos(20, -10);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(compositor.border, HALF_WIDTH, "compositor.border");
+        assert_eq!(compositor.border_y, HALF_HEIGHT + 10, "compositor.border_y");
+
+        assert_eq!(pos0.xpos, 20, "pos0.xpos");
+        assert_eq!(pos0.ypos, -10, "pos0.ypos");
+        assert_eq!(pos0.width, WIDTH, "pos0.width");
+        assert_eq!(pos0.height, HALF_HEIGHT + 20, "pos0.height");
+        assert_eq!(pos0.crop_bottom, HALF_HEIGHT - 20, "pos0.crop_bottom");
+        assert_eq!(pos0.crop_top, 0, "pos0.crop_top");
+
+        assert_eq!(pos1.xpos, 20, "pos1.xpos");
+        assert_eq!(pos1.ypos, HALF_HEIGHT + 10, "pos1.ypos");
+        assert_eq!(pos1.width, WIDTH, "pos1.width");
+        assert_eq!(pos1.height, HALF_HEIGHT - 20, "pos1.height");
+        assert_eq!(pos1.crop_bottom, 0, "pos1.crop_bottom");
+        assert_eq!(pos1.crop_top, HALF_HEIGHT + 20, "pos1.crop_top");
+
+        compositor.move_border_to(HEIGHT + 100);
+        assert_eq!(compositor.border_y, HEIGHT, "border_y inside the bounds");
+        compositor.reset();
+        assert_eq!(compositor.border_y, HALF_HEIGHT, "compositor.border_y");
+    }
+
+    #[test]
+    fn test_topbottom_is_split_transposed() {
+        let mut split = Compositor::new(Mode::Split, HEIGHT, WIDTH);
+        split.set_video_size(HEIGHT, WIDTH);
+        let mut topbottom = Compositor::new(Mode::TopBottom, WIDTH, HEIGHT);
+
+        for _ in 0..5 {
+            split.zoom_in_center_at(100, 200);
+            topbottom.zoom_in_center_at(200, 100);
+        }
+        split.move_pos(-30, 40);
+        topbottom.move_pos(40, -30);
+        split.move_border(-50);
+        topbottom.move_border(-50);
+
+        let (split0, split1) = split.get_positions();
+        let (pos0, pos1) = topbottom.get_positions();
+        for (split, pos) in [(split0, pos0), (split1, pos1)] {
+            assert_eq!(pos.xpos, split.ypos, "xpos");
+            assert_eq!(pos.ypos, split.xpos, "ypos");
+            assert_eq!(pos.width, split.height, "width");
+            assert_eq!(pos.height, split.width, "height");
+            assert_eq!(pos.crop_top, split.crop_left, "crop_top");
+            assert_eq!(pos.crop_bottom, split.crop_right, "crop_bottom");
+        }
+    }
+
+    #[test]
+    fn test_pip_get_positions_default() {
+        let compositor = Compositor::new(Mode::PictureInPicture, WIDTH, HEIGHT);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(pos0.xpos, 0, "pos0.xpos");
+        assert_eq!(pos0.ypos, 0, "pos0.ypos");
+        assert_eq!(pos0.width, WIDTH, "pos0.width");
+        assert_eq!(pos0.height, HEIGHT, "pos0.height");
+        assert_eq!(pos0.crop_right, 0, "pos0.crop_right");
+        assert_eq!(pos0.crop_left, 0, "pos0.crop_left");
+
+        assert_eq!(pos1.xpos, 928, "pos1.xpos");
+        assert_eq!(pos1.ypos, 32, "pos1.ypos");
+        assert_eq!(pos1.width, 320, "pos1.width");
+        assert_eq!(pos1.height, 180, "pos1.height");
+        assert_eq!(pos1.crop_right, 0, "pos1.crop_right");
+        assert_eq!(pos1.crop_left, 0, "pos1.crop_left");
+        assert_eq!(pos1.crop_top, 0, "pos1.crop_top");
+        assert_eq!(pos1.crop_bottom, 0, "pos1.crop_bottom");
+    }
+
+    #[test]
+    fn test_pip_zoom_in_mirrors_view() {
+        let mut compositor = Compositor::new(Mode::PictureInPicture, WIDTH, HEIGHT);
+        compositor.move_inset_to(0, 0);
+        for _ in 0..10 {
+            compositor.zoom_in();
+        }
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(compositor.zoom, 200, "compositor.zoom");
+        assert_eq!(pos0.xpos, -HALF_WIDTH, "pos0.xpos");
+        assert_eq!(pos0.ypos, -HALF_HEIGHT, "pos0.ypos");
+        assert_eq!(pos0.width, 2 * WIDTH, "pos0.width");
+        assert_eq!(pos0.height, 2 * HEIGHT, "pos0.height");
+
+        // the inset shows the same zoomed region: the center of the video
+        assert_eq!(pos1.xpos, 0, "pos1.xpos");
+        assert_eq!(pos1.ypos, 0, "pos1.ypos");
+        assert_eq!(pos1.width, 320, "pos1.width");
+        assert_eq!(pos1.height, 180, "pos1.height");
+        assert_eq!(pos1.crop_left, WIDTH / 4, "pos1.crop_left");
+        assert_eq!(pos1.crop_right, WIDTH / 4, "pos1.crop_right");
+        assert_eq!(pos1.crop_top, HEIGHT / 4, "pos1.crop_top");
+        assert_eq!(pos1.crop_bottom, HEIGHT / 4, "pos1.crop_bottom");
+    }
+
+    #[test]
+    fn test_pip_move_and_resize_inset() {
+        let mut compositor = Compositor::new(Mode::PictureInPicture, WIDTH, HEIGHT);
+        assert!(compositor.is_inside_inset(1000, 100), "inside");
+        assert!(!compositor.is_inside_inset(100, 100), "outside");
+
+        compositor.move_inset_to(-10, 1000);
+        assert_eq!(compositor.inset_x, 0, "compositor.inset_x");
+        assert_eq!(compositor.inset_y, HEIGHT - 180, "compositor.inset_y");
+
+        compositor.resize_inset(320);
+        assert_eq!(compositor.inset_width, 640, "compositor.inset_width");
+        assert_eq!(compositor.get_inset_height(), 360, "inset height");
+        assert_eq!(compositor.inset_y, HEIGHT - 360, "inset inside the bounds");
+
+        compositor.resize_inset(-10000);
+        assert_eq!(compositor.inset_width, INSET_MIN_WIDTH, "min inset");
+
+        compositor.reset();
+        assert_eq!(compositor.inset_width, 320, "compositor.inset_width");
+        assert_eq!(compositor.inset_x, 928, "compositor.inset_x");
+
+        compositor.split_mode();
+        assert!(!compositor.is_inside_inset(1000, 100), "only in pip mode");
+    }
+
+    #[test]
+    fn test_flicker() {
+        let mut compositor = Compositor::new(Mode::Flicker, WIDTH, HEIGHT);
+        compositor.move_pos(10, 20);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(pos0.xpos, 10, "pos0.xpos");
+        assert_eq!(pos0.ypos, 20, "pos0.ypos");
+        assert_eq!(pos0.width, WIDTH, "pos0.width");
+        assert_eq!(pos0.height, HEIGHT, "pos0.height");
+        assert_eq!(pos1.width, 0, "pos1.width");
+
+        compositor.flicker();
+        let (pos0, pos1) = compositor.get_positions();
+        assert_eq!(pos0.width, 0, "pos0.width");
+        assert_eq!(pos1.xpos, 10, "same offset pos1.xpos");
+        assert_eq!(pos1.ypos, 20, "same offset pos1.ypos");
+        assert_eq!(pos1.width, WIDTH, "pos1.width");
+
+        compositor.flicker();
+        compositor.hold_flicker(true);
+        let (pos0, pos1) = compositor.get_positions();
+        assert_eq!(pos0.width, 0, "held pos0.width");
+        assert_eq!(pos1.width, WIDTH, "held pos1.width");
+
+        compositor.hold_flicker(false);
+        let (pos0, _) = compositor.get_positions();
+        assert_eq!(pos0.width, WIDTH, "released pos0.width");
+    }
+
+    #[test]
+    fn test_loupe_hidden() {
+        let compositor = Compositor::default();
+        let (pos2, pos3) = compositor.get_loupe_positions();
+
+        assert_eq!(pos2.width, 0, "pos2.width");
+        assert_eq!(pos3.width, 0, "pos3.width");
+    }
+
+    #[test]
+    fn test_loupe_center() {
+        let mut compositor = Compositor::default();
+        compositor.toggle_loupe();
+        compositor.move_loupe_to(HALF_WIDTH, HALF_HEIGHT);
+        let (x, y, size) = compositor.get_loupe_window();
+        let (pos2, pos3) = compositor.get_loupe_positions();
+
+        assert_eq!(x, HALF_WIDTH + LOUPE_MARGIN, "window x");
+        assert_eq!(y, HALF_HEIGHT + LOUPE_MARGIN, "window y");
+        assert_eq!(size, 240, "window size");
+
+        // 4x: the 240 pixels of the loupe show 60 pixels of the video
+        assert_eq!(pos2.xpos, x, "pos2.xpos");
+        assert_eq!(pos2.ypos, y, "pos2.ypos");
+        assert_eq!(pos2.width, 120, "pos2.width");
+        assert_eq!(pos2.height, 240, "pos2.height");
+        assert_eq!(pos2.crop_left, HALF_WIDTH - 30, "pos2.crop_left");
+        assert_eq!(pos2.crop_right, HALF_WIDTH, "pos2.crop_right");
+        assert_eq!(pos2.crop_top, HALF_HEIGHT - 30, "pos2.crop_top");
+        assert_eq!(pos2.crop_bottom, HALF_HEIGHT - 30, "pos2.crop_bottom");
+
+        assert_eq!(pos3.xpos, x + 120, "pos3.xpos");
+        assert_eq!(pos3.ypos, y, "pos3.ypos");
+        assert_eq!(pos3.width, 120, "pos3.width");
+        assert_eq!(pos3.height, 240, "pos3.height");
+        assert_eq!(pos3.crop_left, HALF_WIDTH, "pos3.crop_left");
+        assert_eq!(pos3.crop_right, HALF_WIDTH - 30, "pos3.crop_right");
+
+        compositor.loupe_zoom_in();
+        compositor.loupe_zoom_in();
+        compositor.loupe_zoom_in();
+        compositor.loupe_zoom_in();
+        compositor.loupe_zoom_in();
+        assert_eq!(compositor.loupe_zoom, 8, "max loupe zoom");
+        let (pos2, _) = compositor.get_loupe_positions();
+        assert_eq!(pos2.crop_left, HALF_WIDTH - 15, "8x pos2.crop_left");
+    }
+
+    #[test]
+    fn test_loupe_window_inside_output() {
+        let mut compositor = Compositor::default();
+        compositor.toggle_loupe();
+        compositor.move_loupe_to(WIDTH - 10, HEIGHT - 10);
+        let (x, y, size) = compositor.get_loupe_window();
+
+        assert_eq!(x, WIDTH - 10 - LOUPE_MARGIN - size, "window x");
+        assert_eq!(y, HEIGHT - 10 - LOUPE_MARGIN - size, "window y");
+    }
+
+    #[test]
+    fn test_loupe_sidebyside() {
+        let mut compositor = Compositor::new_side_by_side(WIDTH, HEIGHT);
+        compositor.toggle_loupe();
+
+        // same point of the video in both halves
+        compositor.move_loupe_to(HALF_WIDTH / 2, HALF_HEIGHT);
+        let (left2, _) = compositor.get_loupe_positions();
+        compositor.move_loupe_to(HALF_WIDTH + HALF_WIDTH / 2, HALF_HEIGHT);
+        let (right2, _) = compositor.get_loupe_positions();
+
+        assert_eq!(left2.crop_left, right2.crop_left, "crop_left");
+        assert_eq!(left2.crop_top, right2.crop_top, "crop_top");
+        assert_eq!(left2.crop_left, HALF_WIDTH - 30, "center of the video");
+    }
+
+    #[test]
+    fn test_exact_zoom() {
+        let mut compositor = Compositor::default();
+        compositor.set_exact_zoom(4);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(pos0.xpos, -3 * HALF_WIDTH, "pos0.xpos");
+        assert_eq!(pos0.ypos, -3 * HALF_HEIGHT, "pos0.ypos");
+        assert_eq!(pos0.width, 4 * HALF_WIDTH, "pos0.width");
+        assert_eq!(pos0.height, 4 * HEIGHT, "pos0.height");
+        assert_eq!(pos0.crop_right, HALF_WIDTH, "pos0.crop_right");
+        assert_eq!(pos1.crop_left, HALF_WIDTH, "pos1.crop_left");
+
+        // aligned to the pixel grid: whole pixels at both sides of the border
+        compositor.move_pos(-3, 0);
+        let (pos0, pos1) = compositor.get_positions();
+        assert_eq!((HALF_WIDTH - pos0.xpos) % 4, 0, "pos0 aligned");
+        assert_eq!(pos0.width % 4, 0, "pos0.width aligned");
+        assert_eq!(pos1.width % 4, 0, "pos1.width aligned");
+        assert_eq!(
+            pos0.width / 4 + pos0.crop_right,
+            WIDTH,
+            "1 video pixel 4 pixels"
+        );
+
+        // zoom steps continue from the exact zoom
+        compositor.zoom_in();
+        assert_eq!(compositor.exact_zoom, None, "compositor.exact_zoom");
+        assert_eq!(compositor.zoom, 410, "compositor.zoom");
+
+        compositor.set_exact_zoom(2);
+        compositor.reset();
+        assert_eq!(compositor.exact_zoom, None, "compositor.exact_zoom");
+        assert_eq!(compositor.zoom, 100, "compositor.zoom");
+    }
+
+    #[test]
+    fn test_exact_zoom_output_bigger_than_video() {
+        let mut compositor = Compositor::new_side_by_side(2 * WIDTH, 2 * HEIGHT);
+        compositor.set_video_size(WIDTH, HEIGHT);
+        compositor.set_exact_zoom(1);
+        let (pos0, pos1) = compositor.get_positions();
+
+        // 1:1 pixels, each half of the output shows the center of the videos
+        assert_eq!(pos0.xpos, 0, "pos0.xpos");
+        assert_eq!(pos0.width, WIDTH, "pos0.width");
+        assert_eq!(pos0.height, HEIGHT, "pos0.height");
+        assert_eq!(pos0.crop_right, 0, "pos0.crop_right");
+        assert_eq!(pos1.xpos, WIDTH, "pos1.xpos");
+        assert_eq!(pos1.width, WIDTH, "pos1.width");
+    }
+
+    #[test]
+    fn test_split_clamped() {
+        let mut compositor = Compositor::default();
+        compositor.toggle_clamped();
+        compositor.move_pos(-1000, 1000);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(compositor.offset_x, 0, "compositor.offset_x");
+        assert_eq!(compositor.offset_y, 0, "compositor.offset_y");
+        assert_eq!(pos0.width, HALF_WIDTH, "pos0.width");
+        assert_eq!(pos1.width, HALF_WIDTH, "pos1.width");
+
+        for _ in 0..10 {
+            compositor.zoom_in();
+        }
+        compositor.move_pos_to(-1000, 1000);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(compositor.offset_x, -HALF_WIDTH, "compositor.offset_x");
+        assert_eq!(compositor.offset_y, HALF_HEIGHT, "compositor.offset_y");
+        assert_eq!(pos0.xpos, -WIDTH, "right edge of the video at the right");
+        assert_eq!(pos0.ypos, 0, "top edge of the video at the top");
+        assert_eq!(pos1.xpos + pos1.width, WIDTH, "pos1 covers the output");
+
+        compositor.toggle_clamped();
+        compositor.move_pos(-1000, 0);
+        assert_eq!(compositor.offset_x, -HALF_WIDTH - 1000, "not clamped");
+    }
+
+    #[test]
+    fn test_sidebyside_clamped() {
+        let mut compositor = Compositor::new_side_by_side(WIDTH, HEIGHT);
+        compositor.toggle_clamped();
+        compositor.move_pos(1000, 1000);
+        let (pos0, pos1) = compositor.get_positions();
+
+        // zoom 100: each video is half of the output, can be moved inside its half
+        assert_eq!(compositor.offset_x, 0, "compositor.offset_x");
+        assert_eq!(compositor.offset_y, HALF_HEIGHT / 2, "compositor.offset_y");
+        assert_eq!(pos0.xpos, 0, "pos0.xpos");
+        assert_eq!(pos0.width, HALF_WIDTH, "pos0.width");
+        assert_eq!(pos1.width, HALF_WIDTH, "pos1.width");
+        assert_eq!(pos0.ypos + pos0.height, HEIGHT, "pos0 inside the output");
+
+        for _ in 0..10 {
+            compositor.zoom_in();
+        }
+        compositor.move_pos_to(-1000, 0);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(
+            pos0.xpos + WIDTH,
+            HALF_WIDTH,
+            "right edge of the video at the border"
+        );
+        assert_eq!(pos1.xpos, HALF_WIDTH, "pos1.xpos");
+        assert_eq!(pos1.crop_left, HALF_WIDTH, "pos1.crop_left");
+    }
+
+    #[test]
+    fn test_bookmark() {
+        let mut compositor = Compositor::default();
+        compositor.side_by_side_mode();
+        compositor.zoom_in();
+        compositor.move_pos(-30, 20);
+        let bookmark = compositor.get_bookmark();
+
+        compositor.reset();
+        compositor.split_mode();
+        assert_ne!(compositor.get_bookmark(), bookmark, "view changed");
+
+        compositor.set_bookmark(&bookmark);
+        assert_eq!(compositor.get_bookmark(), bookmark, "view restored");
+        assert!(compositor.is_side_by_side_mode(), "mode restored");
+        assert_eq!(compositor.zoom, 110, "compositor.zoom");
+
+        assert_eq!(
+            bookmark.toml_table("1"),
+            "[bookmarks.1]\nmode = \"SideBySide\"\nzoom = 110\noffset_x = -30\noffset_y = 20\nborder = 640\nborder_y = 360\n"
+        );
+    }
+
+    #[test]
+    fn test_split_label_positions() {
+        let mut compositor = Compositor::default();
+        let (label0, label1) = compositor.get_label_positions();
+
+        assert_eq!(
+            label0,
+            Some((HALF_WIDTH - LABEL_MARGIN, LABEL_MARGIN)),
+            "label0"
+        );
+        assert_eq!(
+            label1,
+            Some((HALF_WIDTH + LABEL_MARGIN, LABEL_MARGIN)),
+            "label1"
+        );
+
+        // follow the border
+        compositor.move_border(-100);
+        let (label0, label1) = compositor.get_label_positions();
+        assert_eq!(
+            label0,
+            Some((HALF_WIDTH - 100 - LABEL_MARGIN, LABEL_MARGIN)),
+            "label0"
+        );
+        assert_eq!(
+            label1,
+            Some((HALF_WIDTH - 100 + LABEL_MARGIN, LABEL_MARGIN)),
+            "label1"
+        );
+
+        // key 1
+        compositor.move_border_to(WIDTH);
+        let (label0, label1) = compositor.get_label_positions();
+        assert!(label0.is_some(), "label0 shown");
+        assert_eq!(label1, None, "label1 hidden");
+
+        // key 2
+        compositor.move_border_to(0);
+        let (label0, label1) = compositor.get_label_positions();
+        assert_eq!(label0, None, "label0 hidden");
+        assert!(label1.is_some(), "label1 shown");
+    }
+
+    #[test]
+    fn test_sidebyside_label_positions() {
+        let mut compositor = Compositor::new_side_by_side(WIDTH, HEIGHT);
+        let (label0, label1) = compositor.get_label_positions();
+
+        assert_eq!(
+            label0,
+            Some((HALF_WIDTH - LABEL_MARGIN, LABEL_MARGIN)),
+            "label0"
+        );
+        assert_eq!(
+            label1,
+            Some((HALF_WIDTH + LABEL_MARGIN, LABEL_MARGIN)),
+            "label1"
+        );
+
+        compositor.move_pos(2000, 0);
+        let (label0, label1) = compositor.get_label_positions();
+        assert_eq!(label0, None, "label0 out of its half");
+        assert_eq!(label1, None, "label1 out of its half");
+    }
+
+    #[test]
+    fn test_flicker_label_positions() {
+        let mut compositor = Compositor::new(Mode::Flicker, WIDTH, HEIGHT);
+        let (label0, label1) = compositor.get_label_positions();
+        assert!(label0.is_some(), "label0 shown");
+        assert_eq!(label1, None, "label1 hidden");
+
+        compositor.flicker();
+        let (label0, label1) = compositor.get_label_positions();
+        assert_eq!(label0, None, "label0 hidden");
+        assert!(label1.is_some(), "label1 shown");
+    }
+
+    #[test]
+    fn test_split_different_branch_sizes() {
+        let mut compositor = Compositor::default();
+        compositor.set_branch_video_size(1, HALF_WIDTH, HALF_HEIGHT);
+        let (pos0, pos1) = compositor.get_positions();
+
+        assert_eq!(pos0.width, HALF_WIDTH, "pos0.width");
+        assert_eq!(pos0.crop_right, HALF_WIDTH, "pos0.crop_right");
+        assert_eq!(pos1.xpos, HALF_WIDTH, "pos1.xpos");
+        assert_eq!(pos1.width, HALF_WIDTH, "pos1.width");
+        assert_eq!(pos1.crop_left, HALF_WIDTH / 2, "half of the smaller video");
+
+        for _ in 0..10 {
+            compositor.zoom_in();
+        }
+        compositor.move_border_to(0);
+        let (_, pos1) = compositor.get_positions();
+        assert_eq!(pos1.crop_left, HALF_WIDTH / 4, "same region of both videos");
+
+        compositor.top_bottom_mode();
+        compositor.reset();
+        let (pos0, pos1) = compositor.get_positions();
+        assert_eq!(pos0.crop_bottom, HALF_HEIGHT, "pos0.crop_bottom");
+        assert_eq!(pos1.crop_top, HALF_HEIGHT / 2, "half of the smaller video");
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut compositor = Compositor::default();
+        compositor.move_border(-100);
+        let (pos0, pos1) = compositor.get_positions();
+        let (label0, label1) = compositor.get_label_positions();
+
+        compositor.swap();
+        let (swapped0, swapped1) = compositor.get_positions();
+        let (swapped_label0, swapped_label1) = compositor.get_label_positions();
+
+        assert_eq!(swapped0.xpos, pos1.xpos, "first video on the right");
+        assert_eq!(swapped0.width, pos1.width, "first video on the right");
+        assert_eq!(
+            swapped0.crop_left, pos1.crop_left,
+            "first video on the right"
+        );
+        assert_eq!(swapped1.xpos, pos0.xpos, "second video on the left");
+        assert_eq!(
+            swapped1.crop_right, pos0.crop_right,
+            "second video on the left"
+        );
+        assert_eq!(swapped_label0, label0, "label anchors by place");
+        assert_eq!(swapped_label1, label1, "label anchors by place");
+
+        compositor.toggle_loupe();
+        let (loupe0, loupe1) = compositor.get_loupe_positions();
+        assert!(
+            loupe0.xpos > loupe1.xpos,
+            "first video on the right of the loupe"
+        );
+
+        compositor.swap();
+        assert!(!compositor.swapped, "swapped back");
+    }
 }
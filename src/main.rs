@@ -6,21 +6,54 @@
 mod compositor;
 mod metrics;
 mod pipeline;
+#[cfg(feature = "rtsp")]
+mod rtsp;
 mod settings;
+mod snapshot;
+mod stress;
 mod ui;
 
 use gst::prelude::*;
 
 use compositor::Compositor;
-use settings::Settings;
+use settings::{OutputType, Settings};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 const HELP: &str = include_str!("../doc/help.md");
 
+/// Sends EOS into the pipeline on SIGINT/SIGTERM so the muxers and the metrics are
+/// finalized before exiting. A second signal exits without waiting.
+fn add_signal_handler(pipeline: &gst::Pipeline) -> Result<(), anyhow::Error> {
+    let pipeline_weak = pipeline.downgrade();
+    let interrupted = AtomicBool::new(false);
+
+    ctrlc::set_handler(move || {
+        if interrupted.swap(true, Ordering::SeqCst) {
+            println!("forced exit");
+            std::process::exit(1);
+        }
+        println!("interrupted, sending eos");
+        if let Some(pipeline) = pipeline_weak.upgrade() {
+            pipeline.send_event(gst::event::Eos::new());
+        }
+    })?;
+
+    Ok(())
+}
+
 fn run() -> Result<(), anyhow::Error> {
     let settings = Settings::new()?;
+    settings.create_output_dirs()?;
 
-    pipeline::init()?;
+    pipeline::init(&settings)?;
+
+    if settings.get_outputs().contains(&OutputType::Rtsp) {
+        #[cfg(feature = "rtsp")]
+        rtsp::start_server(&settings)?;
+        #[cfg(not(feature = "rtsp"))]
+        anyhow::bail!("Rtsp output needs codeccomp built with the rtsp feature");
+    }
 
     println!("Hello, video codec comparator\n{HELP}");
     if settings.debug {
@@ -33,12 +66,13 @@ fn run() -> Result<(), anyhow::Error> {
     } else {
         compositor::Mode::Split
     };
-    let compositor = Arc::new(Mutex::new(Compositor::new(
-        compositor_mode,
-        settings.input.width,
-        settings.input.height,
-    )));
+    let (output_width, output_height) = settings.get_output_size();
+    let mut compositor = Compositor::new(compositor_mode, output_width, output_height);
+    compositor.set_video_size(settings.input.width, settings.input.height);
+    compositor.clamped = settings.clamp;
+    let compositor = Arc::new(Mutex::new(compositor));
     let metrics = Arc::new(Mutex::new(metrics::Metrics::new(&settings)));
+    let snapshot = Arc::new(Mutex::new(snapshot::Snapshot::new(&settings)));
 
     gst::init().unwrap();
     let pipeline_srt = pipeline::get_srt(&settings);
@@ -48,11 +82,29 @@ fn run() -> Result<(), anyhow::Error> {
         .downcast::<gst::Pipeline>()
         .unwrap();
 
+    if settings.input.is_stress() {
+        stress::add_source(&pipeline, &settings);
+    }
+
+    add_signal_handler(&pipeline)?;
+
+    let start = std::time::Instant::now();
     pipeline
         .set_state(gst::State::Playing)
         .expect("Unable to set the pipeline to the `Playing` state");
 
-    ui::add_probe(&pipeline, state.clone(), compositor.clone(), &settings);
+    ui::add_probe(
+        &pipeline,
+        state.clone(),
+        compositor.clone(),
+        snapshot.clone(),
+        metrics.clone(),
+        &settings,
+    );
+    snapshot::add_probe(&pipeline, snapshot.clone());
+    if !settings.sink_supports_key_navigation() {
+        ui::add_stdin_keys(&pipeline);
+    }
     if settings.metrics {
         metrics::add_probe(&pipeline, metrics.clone(), &settings);
     }
@@ -79,6 +131,13 @@ fn run() -> Result<(), anyhow::Error> {
         };
     }
 
+    if settings.metrics {
+        println!("metrics:\n{}", metrics::report(&pipeline, metrics.clone()));
+    }
+    if settings.batch {
+        println!("batch processed in {:?}", start.elapsed());
+    }
+
     if std::env::var("GST_DEBUG_DUMP_DOT_DIR").as_deref().is_ok() {
         pipeline.debug_to_dot_file(gst::DebugGraphDetails::all(), "codeccomp");
     }
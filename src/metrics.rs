@@ -13,6 +13,8 @@ use crate::Settings;
 #[derive(Default)]
 pub struct EncMetrics {
     name: String,
+    fps_n: u64,
+    fps_d: u64,
     num_buffers: u64,
     num_bytes: u64,
     time_last_buffers: VecDeque<Instant>,
@@ -28,19 +30,27 @@ pub struct Metrics {
     fps_d: u64,
     enc0: EncMetrics,
     enc1: EncMetrics,
+    /// The second encoder is shown in the left column, as its video is in the first position
+    pub swapped: bool,
 }
 
 impl Metrics {
     pub fn new(s: &Settings) -> Self {
         let (fps_n, fps_d) = s.get_framerate();
+        let (enc0_fps_n, enc0_fps_d) = s.get_enc0_framerate();
+        let (enc1_fps_n, enc1_fps_d) = s.get_enc1_framerate();
 
         let enc0 = EncMetrics {
             name: s.get_enc0_name(),
+            fps_n: enc0_fps_n,
+            fps_d: enc0_fps_d,
             time_last_buffers: VecDeque::with_capacity(25),
             ..Default::default()
         };
         let enc1 = EncMetrics {
             name: s.get_enc1_name(),
+            fps_n: enc1_fps_n,
+            fps_d: enc1_fps_d,
             time_last_buffers: VecDeque::with_capacity(25),
             ..Default::default()
         };
@@ -50,6 +60,7 @@ impl Metrics {
             fps_d,
             enc0,
             enc1,
+            swapped: false,
         }
     }
 }
@@ -72,6 +83,11 @@ impl EncMetrics {
         }
     }
 
+    /// Average bytes per second using the framerate of the encoder
+    pub fn bitrate(&self) -> f64 {
+        (self.fps_n * self.num_bytes) as f64 / (self.fps_d * self.num_buffers) as f64
+    }
+
     pub fn avg_processing_time(&self) -> Duration {
         if self.num_buffers != 0 {
             self.total_processing_time / self.num_buffers as u32
@@ -86,43 +102,42 @@ impl fmt::Display for Metrics {
         if self.fps_d != 1 {
             unimplemented!();
         }
+        let (left, right) = if self.swapped {
+            (&self.enc1, &self.enc0)
+        } else {
+            (&self.enc0, &self.enc1)
+        };
 
-        writeln!(
-            f,
-            "{:->20}{:>37}{:->20}",
-            &self.enc0.name, "", &self.enc1.name
-        )?;
+        writeln!(f, "{:->20}{:>37}{:->20}", &left.name, "", &right.name)?;
         writeln!(
             f,
             "{:->14}{:>3}{:>3}{:>37}{:->14}{:>3}{:>3}",
-            self.enc0.num_buffers,
-            self.enc0.max_buffers_inside,
-            self.enc0.time_last_buffers.len(),
+            left.num_buffers,
+            left.max_buffers_inside,
+            left.time_last_buffers.len(),
             "",
-            self.enc1.num_buffers,
-            self.enc1.max_buffers_inside,
-            self.enc1.time_last_buffers.len()
+            right.num_buffers,
+            right.max_buffers_inside,
+            right.time_last_buffers.len()
         )?;
-        let num_bytes0 = human_bytes(self.enc0.num_bytes as f64);
-        let num_bytes1 = human_bytes(self.enc1.num_bytes as f64);
+        let num_bytes0 = human_bytes(left.num_bytes as f64);
+        let num_bytes1 = human_bytes(right.num_bytes as f64);
         writeln!(f, "{:->20}{:>37}{:->20}", num_bytes0, "", num_bytes1)?;
 
-        let bitrate0 =
-            human_bytes((self.fps_n * self.enc0.num_bytes) as f64 / self.enc0.num_buffers as f64);
-        let bitrate1 =
-            human_bytes((self.fps_n * self.enc1.num_bytes) as f64 / self.enc1.num_buffers as f64);
+        let bitrate0 = human_bytes(left.bitrate());
+        let bitrate1 = human_bytes(right.bitrate());
         writeln!(f, "{:->18}/s{:>37}{:->18}/s", bitrate0, "", bitrate1)?;
 
-        let processing_time0 = self.enc0.avg_processing_time();
-        let processing_time1 = self.enc1.avg_processing_time();
+        let processing_time0 = left.avg_processing_time();
+        let processing_time1 = right.avg_processing_time();
         writeln!(
             f,
             "{:->20?}{:>37}{:->20?}",
             processing_time0, "", processing_time1
         )?;
 
-        let cpu_time0 = self.enc0.threads_utime + self.enc0.threads_stime;
-        let cpu_time1 = self.enc1.threads_utime + self.enc1.threads_stime;
+        let cpu_time0 = left.threads_utime + left.threads_stime;
+        let cpu_time1 = right.threads_utime + right.threads_stime;
         writeln!(
             f,
             "{:->8} clock ticks{:>37}{:->8} clock ticks",
@@ -162,29 +177,13 @@ fn add_raw_identity_probe(
             return gst::PadProbeReturn::Ok;
         };
 
-        let stats0 = i0.property::<gst::Structure>("stats");
-        let num_bytes0 = stats0.get::<u64>("num-bytes").unwrap();
-        let num_buffers0 = stats0.get::<u64>("num-buffers").unwrap();
-
         let stats1 = i1.property::<gst::Structure>("stats");
-        let num_bytes1 = stats1.get::<u64>("num-bytes").unwrap();
         let num_buffers1 = stats1.get::<u64>("num-buffers").unwrap();
 
         // TODO no hardcode metrics every second
         if num_buffers1 % fps_n == 0 {
-            let (total_utime0, total_stime0, total_utime1, total_stime1) = get_cpu_usage();
-
             let mut metrics = metrics.lock().unwrap();
-            metrics.enc0.threads_utime = total_utime0;
-            metrics.enc0.threads_stime = total_stime0;
-            metrics.enc1.threads_utime = total_utime1;
-            metrics.enc1.threads_stime = total_stime1;
-
-            metrics.enc0.num_bytes = num_bytes0;
-            metrics.enc0.num_buffers = num_buffers0;
-
-            metrics.enc1.num_bytes = num_bytes1;
-            metrics.enc1.num_buffers = num_buffers1;
+            update(&mut metrics, &i0, &i1);
 
             let metrics_string = format!("{metrics}");
             if settings_debug {
@@ -197,6 +196,41 @@ fn add_raw_identity_probe(
     });
 }
 
+/// Updates the metrics with the stats of the identities after the encoders and the cpu usage
+fn update(metrics: &mut Metrics, i0: &gst::Element, i1: &gst::Element) {
+    let stats0 = i0.property::<gst::Structure>("stats");
+    let num_bytes0 = stats0.get::<u64>("num-bytes").unwrap();
+    let num_buffers0 = stats0.get::<u64>("num-buffers").unwrap();
+
+    let stats1 = i1.property::<gst::Structure>("stats");
+    let num_bytes1 = stats1.get::<u64>("num-bytes").unwrap();
+    let num_buffers1 = stats1.get::<u64>("num-buffers").unwrap();
+
+    let (total_utime0, total_stime0, total_utime1, total_stime1) = get_cpu_usage();
+
+    metrics.enc0.threads_utime = total_utime0;
+    metrics.enc0.threads_stime = total_stime0;
+    metrics.enc1.threads_utime = total_utime1;
+    metrics.enc1.threads_stime = total_stime1;
+
+    metrics.enc0.num_bytes = num_bytes0;
+    metrics.enc0.num_buffers = num_buffers0;
+
+    metrics.enc1.num_bytes = num_bytes1;
+    metrics.enc1.num_buffers = num_buffers1;
+}
+
+/// Returns the final metrics, updated with the last stats of the pipeline
+pub fn report(pipeline: &gst::Pipeline, metrics: Arc<Mutex<Metrics>>) -> String {
+    let i0 = pipeline.by_name("i0").unwrap();
+    let i1 = pipeline.by_name("i1").unwrap();
+
+    let mut metrics = metrics.lock().unwrap();
+    update(&mut metrics, &i0, &i1);
+
+    format!("{metrics}")
+}
+
 fn add_encoder_probes(pipeline: &gst::Pipeline, metrics: Arc<Mutex<Metrics>>) {
     let enc0 = pipeline.by_name("enc0").unwrap();
     let dec0 = pipeline.by_name("dec0").unwrap();
@@ -365,6 +399,8 @@ mod tests {
         assert_eq!(metrics.fps_d, 1, "metrics.fps_d");
 
         assert_ne!(metrics.enc1.name, "", "metrics.enc0.name not empty");
+        assert_eq!(metrics.enc0.fps_n, 30, "metrics.enc0.fps_n");
+        assert_eq!(metrics.enc0.fps_d, 1, "metrics.enc0.fps_d");
         assert_eq!(metrics.enc0.num_buffers, 0, "metrics.enc0.num_buffers");
         assert_eq!(metrics.enc0.num_bytes, 0, "metrics.enc0.num_bytes");
         assert_eq!(
@@ -407,6 +443,21 @@ mod tests {
         assert_eq!(metrics.enc1.threads_stime, 0, "metrics.enc1.threads_stime");
     }
 
+    #[test]
+    fn test_bitrate_scaled_framerate() {
+        let mut s = Settings::default();
+        s.encoder1.framerate = Some("15/1".to_string());
+        let mut metrics = Metrics::new(&s);
+
+        metrics.enc0.num_buffers = 30;
+        metrics.enc0.num_bytes = 3000;
+        metrics.enc1.num_buffers = 15;
+        metrics.enc1.num_bytes = 3000;
+
+        assert_eq!(metrics.enc0.bitrate(), 3000.0, "metrics.enc0.bitrate");
+        assert_eq!(metrics.enc1.bitrate(), 3000.0, "metrics.enc1.bitrate");
+    }
+
     #[test]
     #[should_panic]
     fn test_buffer_out_no_in() {
@@ -613,4 +664,26 @@ mod tests {
             "metrics.enc0.total_processing_time"
         );
     }
+
+    #[test]
+    fn test_swapped_columns() {
+        let mut metrics = Metrics::new(&Settings::default());
+        metrics.enc0.name = "left".to_string();
+        metrics.enc1.name = "right".to_string();
+
+        let header = metrics.to_string();
+        let header = header.lines().next().unwrap();
+        assert!(
+            header.find("left") < header.find("right"),
+            "first encoder in the left column"
+        );
+
+        metrics.swapped = true;
+        let header = metrics.to_string();
+        let header = header.lines().next().unwrap();
+        assert!(
+            header.find("right") < header.find("left"),
+            "second encoder in the left column when swapped"
+        );
+    }
 }
@@ -1,10 +1,16 @@
 use crate::compositor::Position;
+use crate::settings::VideoSinkType;
 use crate::Compositor;
 use crate::Settings;
 
 use gst::prelude::*;
 
-pub fn init() -> Result<(), anyhow::Error> {
+pub fn init(settings: &Settings) -> Result<(), anyhow::Error> {
+    if settings.output.sink == VideoSinkType::gtk4paintablesink {
+        // gtk4paintablesink only opens a window with this env var
+        std::env::set_var("GST_GTK4_WINDOW", "1");
+    }
+
     gst::init()?;
     gstoriginalbuffer::plugin_register_static()?;
     Ok(())
@@ -12,25 +18,46 @@ pub fn init() -> Result<(), anyhow::Error> {
 
 pub fn get_srt(settings: &Settings) -> String {
     let src = settings.get_pipeline_src();
+    let scale0 = settings.get_pipeline_scale0();
     let enc0 = settings.get_pipeline_enc0();
+    let save_tee0 = settings.get_pipeline_save_tee0();
+    let save0 = settings.get_pipeline_save0();
     let dec0 = settings.get_pipeline_dec0();
+    let upscale0 = settings.get_pipeline_upscale0();
+    let dump_tee0 = settings.get_pipeline_dump_tee0();
+    let loupe_tee0 = settings.get_pipeline_loupe_tee0();
+    let scale1 = settings.get_pipeline_scale1();
     let enc1 = settings.get_pipeline_enc1();
+    let save_tee1 = settings.get_pipeline_save_tee1();
+    let save1 = settings.get_pipeline_save1();
     let dec1 = settings.get_pipeline_dec1();
+    let upscale1 = settings.get_pipeline_upscale1();
+    let dump_tee1 = settings.get_pipeline_dump_tee1();
+    let loupe_tee1 = settings.get_pipeline_loupe_tee1();
+    let dump = settings.get_pipeline_dump();
+    let loupe = settings.get_pipeline_loupe();
     let sink = settings.get_pipeline_sink();
     let compositor = settings.get_pipeline_compositor();
     let font = settings.get_metrics_font();
+    let label0 = settings.get_enc_label(0);
+    let label1 = settings.get_enc_label(1);
 
     //TODO(-100) handle no opengl pipelines with compositor and videotestsrc
-    //TODO(-10) handle to use glimagesinkelement (no KeyPress) or gtk4paintablesink (Note no NavigationEvent and env var GST_GTK4_WINDOW=1 needed)
     let pipeline_srt = format!(
         r#"
         {src} !
         queue ! originalbuffersave ! tee name=tee_src
-        tee_src.src_0 ! queue name=enc0 ! {enc0} ! queue name=dec0 !
-        identity name=i0 ! {dec0} ! videocrop name=crop0 ! queue name=end0 ! mix.sink_0
-        tee_src.src_1 ! queue name=enc1 ! {enc1} ! queue name=dec1 !
-        identity name=i1 ! {dec1} ! videocrop name=crop1 ! queue name=end1 ! mix.sink_1
+        tee_src.src_0 ! {scale0}queue name=enc0 ! {enc0} ! {save_tee0}queue name=dec0 !
+        identity name=i0 ! {dec0} ! identity name=decoded0 ! {upscale0}{dump_tee0}{loupe_tee0}videocrop name=crop0 ! queue name=end0 ! mix.sink_0
+        {save0}
+        tee_src.src_1 ! {scale1}queue name=enc1 ! {enc1} ! {save_tee1}queue name=dec1 !
+        identity name=i1 ! {dec1} ! identity name=decoded1 ! {upscale1}{dump_tee1}{loupe_tee1}videocrop name=crop1 ! queue name=end1 ! mix.sink_1
+        {save1}
+        {dump}
+        {loupe}
         {compositor} name=mix  !
+        textoverlay name=label0 text="{label0}" halignment=right valignment=top xpad=0 ypad=0 shaded-background=1 font-desc="{font}" !
+        textoverlay name=label1 text="{label1}" halignment=left valignment=top xpad=0 ypad=0 shaded-background=1 font-desc="{font}" !
         textoverlay name=metrics valignment=bottom font-desc="{font}" !
         {sink}
     "#
@@ -51,74 +78,178 @@ pub fn get_srt(settings: &Settings) -> String {
     pipeline_srt
 }
 
-fn fix_pos(pos: &mut Position, width: i32, compositor_supports_crop: bool) {
+fn fix_pos(
+    pos: &mut Position,
+    width: i32,
+    height: i32,
+    video_width: i32,
+    video_height: i32,
+    compositor_supports_crop: bool,
+) {
     // workaround to handle gst issue when width==0 with any video mixers
     // see `glvideomixer sink_0::width=0` in README.md
     if pos.width == 0 {
         pos.width = width;
         pos.xpos = width;
     }
+    if pos.height == 0 {
+        pos.height = height;
+        pos.ypos = height;
+    }
 
     // workaround to handle gst issue when crop==total_width with compositor and vacompositor
     // see `compositor and vacompositor video out of the box` in README.md
     if !compositor_supports_crop {
-        if pos.crop_right == width {
-            pos.crop_right = width - 10;
+        if pos.crop_right == video_width {
+            pos.crop_right = video_width - 10;
+        }
+
+        if pos.crop_left == video_width {
+            pos.crop_left = video_width - 10;
+        }
+
+        if pos.crop_top == video_height {
+            pos.crop_top = video_height - 10;
         }
 
-        if pos.crop_left == width {
-            pos.crop_left = width - 10;
+        if pos.crop_bottom == video_height {
+            pos.crop_bottom = video_height - 10;
         }
     }
 }
 
-pub fn update_mixer(
-    compositor: &Compositor,
-    mixer_sink_0_pad: &gst::Pad,
-    mixer_sink_1_pad: &gst::Pad,
-    crop0: &gst::Element,
-    crop1: &gst::Element,
+/// Pads of the mixer with the `videocrop`s linked to them, the compared videos and the
+/// loupe (if enabled). The `videocrop`s are used when the mixer pads do not support crop.
+#[derive(Clone)]
+pub struct Mixer {
+    mixer: gst::Element,
+    pads: Vec<gst::Pad>,
+    crops: Vec<gst::Element>,
+    labels: [gst::Element; 2],
+    names: [String; 2],
     compositor_supports_crop: bool,
-) {
-    let (mut pos0, mut pos1) = compositor.get_positions();
-
-    fix_pos(&mut pos0, compositor.width, compositor_supports_crop);
-    fix_pos(&mut pos1, compositor.width, compositor_supports_crop);
-
-    //TODO refactor avoid copy and paste
-    if compositor_supports_crop {
-        mixer_sink_0_pad.set_properties(&[
-            ("width", &pos0.width),
-            ("height", &pos0.height),
-            ("xpos", &pos0.xpos),
-            ("ypos", &pos0.ypos),
-            ("crop-right", &pos0.crop_right),
-        ]);
-
-        mixer_sink_1_pad.set_properties(&[
-            ("width", &pos1.width),
-            ("height", &pos1.height),
-            ("xpos", &pos1.xpos),
-            ("ypos", &pos1.ypos),
-            ("crop-left", &pos1.crop_left),
-        ]);
-    } else {
-        mixer_sink_0_pad.set_properties(&[
-            ("width", &pos0.width),
-            ("height", &pos0.height),
-            ("xpos", &pos0.xpos),
-            ("ypos", &pos0.ypos),
-        ]);
-
-        mixer_sink_1_pad.set_properties(&[
-            ("width", &pos1.width),
-            ("height", &pos1.height),
-            ("xpos", &pos1.xpos),
-            ("ypos", &pos1.ypos),
-        ]);
-
-        crop0.set_property("right", pos0.crop_right);
-        crop1.set_property("left", pos1.crop_left);
+}
+
+impl Mixer {
+    pub fn new(pipeline: &gst::Pipeline, settings: &Settings) -> Self {
+        let mixer = pipeline.by_name("mix").unwrap();
+        let num_pads = if settings.loupe { 4 } else { 2 };
+
+        let pads: Vec<gst::Pad> = (0..num_pads)
+            .map(|i| mixer.static_pad(&format!("sink_{i}")).unwrap())
+            .collect();
+        let crops = (0..num_pads)
+            .map(|i| pipeline.by_name(&format!("crop{i}")).unwrap())
+            .collect();
+
+        // loupe over the compared videos
+        for (zorder, pad) in pads.iter().enumerate() {
+            pad.set_property("zorder", zorder as u32);
+        }
+
+        Self {
+            mixer,
+            pads,
+            crops,
+            labels: [
+                pipeline.by_name("label0").unwrap(),
+                pipeline.by_name("label1").unwrap(),
+            ],
+            names: [settings.get_enc_label(0), settings.get_enc_label(1)],
+            compositor_supports_crop: settings.gst_pipeline_compositor_supports_crop(),
+        }
+    }
+
+    /// Only some mixers (as d3d12compositor) can scale with nearest-neighbour
+    pub fn supports_nearest(&self) -> bool {
+        self.mixer.find_property("sampling-method").is_some()
+    }
+
+    pub fn update(&self, compositor: &Compositor) {
+        if self.supports_nearest() {
+            let sampling_method = if compositor.nearest {
+                "nearest"
+            } else {
+                "bilinear"
+            };
+            self.mixer
+                .set_property_from_str("sampling-method", sampling_method);
+        }
+
+        // the video in the second position (the picture in picture inset) over the first one
+        self.pads[0].set_property("zorder", compositor.swapped as u32);
+        self.pads[1].set_property("zorder", !compositor.swapped as u32);
+
+        let (pos0, pos1) = compositor.get_positions();
+        let (pos2, pos3) = compositor.get_loupe_positions();
+
+        for (index, ((mut pos, pad), crop)) in [pos0, pos1, pos2, pos3]
+            .into_iter()
+            .zip(&self.pads)
+            .zip(&self.crops)
+            .enumerate()
+        {
+            // the loupe pads show the decoded videos of the branches too
+            let (video_width, video_height) = compositor.video_sizes[index % 2];
+            fix_pos(
+                &mut pos,
+                compositor.width,
+                compositor.height,
+                video_width,
+                video_height,
+                self.compositor_supports_crop,
+            );
+
+            pad.set_properties(&[
+                ("width", &pos.width),
+                ("height", &pos.height),
+                ("xpos", &pos.xpos),
+                ("ypos", &pos.ypos),
+            ]);
+
+            if self.compositor_supports_crop {
+                pad.set_properties(&[
+                    ("crop-right", &pos.crop_right),
+                    ("crop-left", &pos.crop_left),
+                    ("crop-top", &pos.crop_top),
+                    ("crop-bottom", &pos.crop_bottom),
+                ]);
+            } else {
+                crop.set_properties(&[
+                    ("right", &pos.crop_right),
+                    ("left", &pos.crop_left),
+                    ("top", &pos.crop_top),
+                    ("bottom", &pos.crop_bottom),
+                ]);
+            }
+        }
+
+        self.update_labels(compositor);
+    }
+
+    /// Moves the encoder labels next to the border, hidden when their video is hidden. The
+    /// labels belong to the positions, their names are swapped with the videos.
+    fn update_labels(&self, compositor: &Compositor) {
+        let (anchor0, anchor1) = compositor.get_label_positions();
+
+        let swapped = compositor.swapped as usize;
+        self.labels[0].set_property("text", &self.names[swapped]);
+        self.labels[1].set_property("text", &self.names[1 - swapped]);
+
+        // label0 is right aligned, its deltax is relative to the right edge
+        for (label, anchor, offset_x) in [
+            (&self.labels[0], anchor0, -compositor.width),
+            (&self.labels[1], anchor1, 0),
+        ] {
+            match anchor {
+                Some((x, y)) => label.set_properties(&[
+                    ("silent", &false),
+                    ("deltax", &(x + offset_x)),
+                    ("deltay", &y),
+                ]),
+                None => label.set_property("silent", true),
+            }
+        }
     }
 }
 
@@ -137,9 +268,11 @@ mod tests {
             height: 288,
             crop_right: 720,
             crop_left: 0,
+            crop_top: 0,
+            crop_bottom: 0,
         };
 
-        fix_pos(&mut pos, width, true);
+        fix_pos(&mut pos, width, 576, width, 576, true);
         assert_eq!(pos.xpos, width);
         assert_eq!(pos.ypos, 216);
         assert_eq!(pos.width, width);
@@ -158,9 +291,11 @@ mod tests {
             height: 288,
             crop_right: 720,
             crop_left: 0,
+            crop_top: 0,
+            crop_bottom: 0,
         };
 
-        fix_pos(&mut pos, width, false);
+        fix_pos(&mut pos, width, 576, width, 576, false);
         assert_eq!(pos.xpos, width);
         assert_eq!(pos.ypos, 216);
         assert_eq!(pos.width, width);
@@ -196,12 +331,11 @@ mod tests {
     fn test_tour(backend: BackendType) {
         let mut settings = Settings::default();
         settings.backend = backend;
-        let compositor_supports_crop: bool = settings.gst_pipeline_compositor_supports_crop();
         let mut compositor = Compositor::new_split(settings.input.width, settings.input.height);
 
         //TODO refactor this logic with main
 
-        init().unwrap();
+        init(&settings).unwrap();
         let pipeline_srt = get_srt(&settings);
 
         let pipeline = gst::parse::launch(&pipeline_srt)
@@ -209,22 +343,8 @@ mod tests {
             .downcast::<gst::Pipeline>()
             .unwrap();
 
-        let mixer = pipeline.by_name("mix").unwrap();
-        let crop0 = pipeline.by_name("crop0").unwrap();
-        let crop1 = pipeline.by_name("crop1").unwrap();
-        let mixer_sink_0_pad = mixer.static_pad("sink_0").unwrap();
-        let mixer_sink_1_pad = mixer.static_pad("sink_1").unwrap();
-
-        let update_mixer_fn = |compositor: &Compositor| {
-            update_mixer(
-                compositor,
-                &mixer_sink_0_pad,
-                &mixer_sink_1_pad,
-                &crop0,
-                &crop1,
-                compositor_supports_crop,
-            );
-        };
+        let mixer = Mixer::new(&pipeline, &settings);
+        let update_mixer_fn = |compositor: &Compositor| mixer.update(compositor);
 
         pipeline
             .set_state(gst::State::Playing)
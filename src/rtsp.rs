@@ -0,0 +1,38 @@
+use gst::glib;
+use gst_rtsp_server::prelude::*;
+
+use crate::settings::RTSP_CHANNEL;
+use crate::Settings;
+
+const MOUNT: &str = "/codeccomp";
+
+/// Serves the composited view (received from the `intervideosink` of the `Rtsp` output)
+/// in rtsp://<host>:<rtsp_port>/codeccomp
+pub fn start_server(settings: &Settings) -> Result<(), anyhow::Error> {
+    let port = settings.get_rtsp_port();
+    let framerate = &settings.input.framerate;
+
+    let server = gst_rtsp_server::RTSPServer::new();
+    server.set_service(&port.to_string());
+
+    let factory = gst_rtsp_server::RTSPMediaFactory::new();
+    factory.set_launch(&format!(
+        "( intervideosrc channel={RTSP_CHANNEL} ! video/x-raw, framerate={framerate} ! videoconvert ! video/x-raw, format=I420 ! x264enc tune=zerolatency speed-preset=ultrafast bitrate=8192 key-int-max=30 ! rtph264pay name=pay0 pt=96 )"
+    ));
+    // All the clients watch the same session
+    factory.set_shared(true);
+
+    let mounts = server
+        .mount_points()
+        .ok_or_else(|| anyhow::anyhow!("RTSP server w/o mount points"))?;
+    mounts.add_factory(MOUNT, factory);
+
+    let _source_id = server.attach(None)?;
+    // The server is attached to the default main context, so it needs a main loop
+    std::thread::spawn(|| {
+        glib::MainLoop::new(None, false).run();
+    });
+
+    println!("RTSP server ready at rtsp://127.0.0.1:{port}{MOUNT}");
+    Ok(())
+}
@@ -1,9 +1,16 @@
 use config::{Config, ConfigError, Environment, File};
 use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::compositor::Bookmark;
 
 const WIDTH: i32 = 1280;
 const HEIGHT: i32 = 720;
 const FRAMERATE: &str = "30/1";
+pub const RTSP_CHANNEL: &str = "codeccomp";
+pub const BOOKMARKS_FILE: &str = "bookmarks.toml";
+const BATCH_NUM_BUFFERS: u32 = 1000;
+const FLICKER_INTERVAL: u32 = 15;
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize, PartialEq, Default)]
@@ -22,6 +29,21 @@ pub enum InputType {
     #[default]
     Test,
     Camera,
+    ImageSequence,
+    Network,
+    Screen,
+    Stress,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Default, Clone, Copy)]
+pub enum StressScene {
+    #[default]
+    ScrollingText,
+    FilmGrain,
+    FastPan,
+    SceneCuts,
+    Gradient,
+    Flashing,
 }
 
 fn default_framerate() -> String {
@@ -36,6 +58,9 @@ fn default_height() -> i32 {
 fn default_true() -> bool {
     true
 }
+fn default_flicker_interval() -> u32 {
+    FLICKER_INTERVAL
+}
 
 #[derive(Debug, Deserialize)]
 pub struct Input {
@@ -50,6 +75,16 @@ pub struct Input {
     pub input: InputType,
     pub pattern: Option<String>,
     pub num_buffers: Option<u32>,
+    pub location: Option<String>,
+    pub uri: Option<String>,
+    pub encoding: Option<String>,
+    pub display: Option<String>,
+    pub screen_x: Option<u32>,
+    pub screen_y: Option<u32>,
+    #[serde(default)]
+    pub scene: StressScene,
+    #[serde(default)]
+    pub seed: u64,
 }
 
 impl Default for Input {
@@ -62,13 +97,86 @@ impl Default for Input {
             input: InputType::default(),
             pattern: None,
             num_buffers: None,
+            location: None,
+            uri: None,
+            encoding: None,
+            display: None,
+            screen_x: None,
+            screen_y: None,
+            scene: StressScene::default(),
+            seed: 0,
         }
     }
 }
 
 impl Input {
-    fn is_test(&self) -> bool {
-        self.input == InputType::Test
+    pub fn is_stress(&self) -> bool {
+        self.input == InputType::Stress
+    }
+}
+
+/// Returns the lowercase extension of the file `location`
+fn get_extension(location: &str) -> String {
+    location
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// Returns the tee named `name` of an optional branch, empty when the branch is not `enabled`
+fn get_pipeline_tee(name: &str, enabled: bool) -> String {
+    if enabled {
+        format!("tee name={name} ! ")
+    } else {
+        "".to_string()
+    }
+}
+
+/// Returns the encoding of the composited view into `location` using the file extension.
+/// I420 keeps the H.264 in a profile that any player can decode.
+/// Note: mp4 files are only valid after an EOS.
+fn get_pipeline_record(location: &str) -> String {
+    let extension = get_extension(location);
+
+    let mux = match extension.as_str() {
+        "mp4" => "mp4mux",
+        "mkv" => "matroskamux",
+        _ => panic!("record location must end with .mp4 or .mkv"),
+    };
+
+    format!("videoconvert ! video/x-raw, format=I420 ! x264enc pass=qual quantizer=18 speed-preset=veryfast tune=zerolatency ! h264parse ! {mux} ! filesink location=\"{location}\" sync=false")
+}
+
+/// Returns the encoding of the composited view streamed to `uri`:
+///  * `rtp://host:port` RTP over UDP
+///  * `srt://host:port?mode=listener|caller` MPEG-TS over SRT
+///
+/// In I420 as the record, for the players and hardware decoders of the viewers.
+fn get_pipeline_stream(uri: &str) -> String {
+    let enc = "videoconvert ! video/x-raw, format=I420 ! x264enc tune=zerolatency speed-preset=ultrafast bitrate=8192 key-int-max=30 ! h264parse config-interval=-1";
+
+    if let Some(address) = uri.strip_prefix("rtp://") {
+        let (host, port) = address
+            .rsplit_once(':')
+            .expect("rtp stream uri must be rtp://host:port");
+        format!("{enc} ! rtph264pay ! udpsink host={host} port={port} sync=false")
+    } else if uri.starts_with("srt://") {
+        format!("{enc} ! mpegtsmux ! srtsink uri=\"{uri}\" sync=false")
+    } else {
+        panic!("stream uri must start with rtp:// or srt://");
+    }
+}
+
+/// Returns the caps and the decoder of the frames of an image sequence using the file extension.
+fn get_image_caps_and_decoder(location: &str) -> (&'static str, &'static str) {
+    let extension = get_extension(location);
+
+    match extension.as_str() {
+        "png" => ("image/png", "pngdec"),
+        "jpg" | "jpeg" => ("image/jpeg", "jpegdec"),
+        "tif" | "tiff" => ("image/tiff", "avdec_tiff"),
+        _ => panic!("image sequence location must end with .png, .jpg, .jpeg, .tif or .tiff"),
     }
 }
 
@@ -100,6 +208,13 @@ pub struct Encoder {
     pub custom: Option<String>,
     #[serde(default = "default_decoder")]
     pub decoder: String,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub framerate: Option<String>,
+    /// Scales the decoded video back to the input size, otherwise the mixer scales it
+    #[serde(default = "default_true")]
+    pub upscale: bool,
+    pub save_path: Option<String>,
 }
 impl Default for Encoder {
     fn default() -> Self {
@@ -108,9 +223,19 @@ impl Default for Encoder {
             bitrate: default_bitrate(),
             custom: None,
             decoder: default_decoder(),
+            width: None,
+            height: None,
+            framerate: None,
+            upscale: true,
+            save_path: None,
         }
     }
 }
+impl Encoder {
+    fn is_scaled(&self) -> bool {
+        self.width.is_some() || self.height.is_some() || self.framerate.is_some()
+    }
+}
 fn default_enc0() -> Encoder {
     Encoder {
         bitrate: 256,
@@ -118,6 +243,45 @@ fn default_enc0() -> Encoder {
     }
 }
 
+#[allow(non_camel_case_types)]
+#[derive(Debug, Deserialize, PartialEq, Default)]
+pub enum VideoSinkType {
+    /// xvimagesink, d3d12videosink or osxvideosink depending on the platform
+    #[default]
+    auto,
+    glimagesink,
+    gtk4paintablesink,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+pub enum OutputType {
+    /// Video sink set with `sink`
+    Display,
+    /// File set with `record`
+    Record,
+    /// Network stream set with `stream`
+    Stream,
+    /// RTSP server in `rtsp_port` (needs the rtsp feature)
+    Rtsp,
+    Fake,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct Output {
+    /// If not defined `Display` (or `Fake` with `nooutput`) and `Record` if `record` is defined
+    pub sinks: Option<Vec<OutputType>>,
+    #[serde(default)]
+    pub sink: VideoSinkType,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub record: Option<String>,
+    pub stream: Option<String>,
+    #[cfg_attr(not(feature = "rtsp"), allow(dead_code))]
+    pub rtsp_port: Option<u16>,
+    pub dump_dir: Option<String>,
+    pub snapshot_dir: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(unused)]
 pub struct Settings {
@@ -130,13 +294,29 @@ pub struct Settings {
     #[serde(default)]
     pub backend: BackendType,
     #[serde(default)]
+    pub output: Output,
+    #[serde(default)]
     pub sidebyside: bool,
+    /// Frames between the flicker mode toggles, 0 to toggle only while space is held
+    #[serde(default = "default_flicker_interval")]
+    pub flicker_interval: u32,
+    /// Clamped navigation (key c), panning never leaves the videos
+    #[serde(default)]
+    pub clamp: bool,
+    /// Adds the mixer pads of the loupe (key l)
+    #[serde(default)]
+    pub loupe: bool,
     #[serde(default)]
     pub nooutput: bool,
     #[serde(default)]
+    pub batch: bool,
+    #[serde(default)]
     pub debug: bool,
     #[serde(default = "default_true")]
     pub metrics: bool,
+    /// Views saved with Ctrl+number, by number
+    #[serde(default)]
+    pub bookmarks: BTreeMap<String, Bookmark>,
 }
 impl Default for Settings {
     fn default() -> Self {
@@ -144,27 +324,77 @@ impl Default for Settings {
         let encoder0 = default_enc0();
         let encoder1 = Encoder::default();
         let backend = BackendType::default();
+        let output = Output::default();
 
         Self {
             input,
             encoder0,
             encoder1,
             backend,
+            output,
             sidebyside: false,
+            flicker_interval: FLICKER_INTERVAL,
+            clamp: false,
+            loupe: false,
             nooutput: false,
+            batch: false,
             debug: false,
             metrics: true,
+            bookmarks: BTreeMap::new(),
         }
     }
 }
+/// Writes the bookmarks in the TOML file loaded by `Settings::new`
+pub fn save_bookmarks(path: &str, bookmarks: &BTreeMap<String, Bookmark>) -> std::io::Result<()> {
+    let toml = bookmarks
+        .iter()
+        .map(|(name, bookmark)| bookmark.toml_table(name))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    std::fs::write(path, toml)
+}
+
 impl Settings {
     pub fn new() -> Result<Self, ConfigError> {
         let s = Config::builder()
             .add_source(File::with_name("config.toml").required(false))
+            .add_source(File::with_name(BOOKMARKS_FILE).required(false))
             .add_source(Environment::with_prefix("CODECCOMP").separator("__"))
             .build()?;
 
-        s.try_deserialize()
+        let settings: Self = s.try_deserialize()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Checks the settings that can not work together
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.batch {
+            return Ok(());
+        }
+
+        if matches!(
+            self.input.input,
+            InputType::Camera | InputType::Network | InputType::Screen
+        ) {
+            return Err(ConfigError::Message(format!(
+                "batch mode needs a non-live input (Test, ImageSequence or Stress), not {:?}",
+                self.input.input
+            )));
+        }
+        if let Some(output) = self.get_outputs().into_iter().find(|output| {
+            matches!(
+                output,
+                OutputType::Display | OutputType::Stream | OutputType::Rtsp
+            )
+        }) {
+            return Err(ConfigError::Message(format!(
+                "batch mode does not support the real time {output:?} output"
+            )));
+        }
+
+        Ok(())
     }
 
     pub fn get_pipeline_src(&self) -> String {
@@ -178,34 +408,160 @@ impl Settings {
             .map(|s| format!("! video/x-raw, format={}", s))
             .unwrap_or_default();
         let num_buffers = self
-            .input
-            .num_buffers
+            .get_num_buffers()
             .map(|s| format!(" num-buffers={}", s))
             .unwrap_or_default();
+        let is_live = if self.batch { 0 } else { 1 };
+        // identity sync=1 paces the non-live sources at the configured framerate
+        let sync = if self.batch { 0 } else { 1 };
+
+        match self.input.input {
+            InputType::Test => {
+                // pattern=smpte
+                let pattern = self
+                    .input
+                    .pattern
+                    .clone()
+                    .unwrap_or("mandelbrot".to_string());
+
+                format!("gltestsrc is-live={is_live} pattern={pattern} {num_buffers} name=src  ! video/x-raw(memory:GLMemory), framerate={framerate}, width={width}, height={height}, pixel-aspect-ratio=1/1 ! glcolorconvert ! gldownload {format}")
+            }
+            InputType::Camera => {
+                let src = if cfg!(target_os = "linux") {
+                    "v4l2src"
+                } else if cfg!(target_os = "windows") {
+                    "mfvideosrc"
+                } else if cfg!(target_os = "macos") {
+                    //hardcoded until gst::DeviceMonitor
+                    return "avfvideosrc ! video/x-raw, width=1280, height=720, format=NV12, framerate=30/1 ! videoconvertscale ! videorate ".to_string();
+                } else {
+                    unimplemented!()
+                };
+
+                format!("{src} {num_buffers} ! image/jpeg, width={width}, height={height}, framerate={framerate} ! jpegdec ! videoconvertscale ! videorate {format}")
+            }
+            InputType::ImageSequence => {
+                let location = self
+                    .input
+                    .location
+                    .clone()
+                    .expect("image sequence input w/o location value");
+                let (caps, dec) = get_image_caps_and_decoder(&location);
+                let stop_index = self
+                    .get_num_buffers()
+                    .map(|s| format!(" stop-index={}", s.saturating_sub(1)))
+                    .unwrap_or_default();
+
+                format!("multifilesrc location=\"{location}\" index=0 {stop_index} caps=\"{caps}, framerate={framerate}\" name=src ! {dec} ! videoconvertscale ! videorate ! video/x-raw, width={width}, height={height}, framerate={framerate}, pixel-aspect-ratio=1/1 ! identity sync={sync} {format}")
+            }
+            InputType::Network => {
+                let uri = self.input.uri.clone().expect("network input w/o uri value");
+                let src = self.get_pipeline_network_src(&uri);
+
+                format!("{src} ! decodebin3 ! videoconvertscale ! videorate ! video/x-raw, width={width}, height={height}, framerate={framerate}, pixel-aspect-ratio=1/1 {format}")
+            }
+            InputType::Screen => {
+                let src = self.get_pipeline_screen_src(&num_buffers);
+
+                format!("{src} ! videoconvertscale ! videorate ! video/x-raw, width={width}, height={height}, framerate={framerate}, pixel-aspect-ratio=1/1 {format}")
+            }
+            InputType::Stress => {
+                // frames pushed by stress::add_source
+                format!("appsrc name=src format=time caps=\"video/x-raw, format=I420, width={width}, height={height}, framerate={framerate}, pixel-aspect-ratio=1/1\" ! identity sync={sync} ! videoconvert {format}")
+            }
+        }
+    }
+
+    /// Returns the network source for `uri`:
+    ///  * `rtp://host:port` RTP over UDP, payload set with `encoding` (H264 by default)
+    ///  * `udp://host:port` MPEG-TS over UDP (demuxed by decodebin3)
+    ///  * `srt://host:port?mode=listener|caller` MPEG-TS over SRT
+    fn get_pipeline_network_src(&self, uri: &str) -> String {
+        if let Some(address) = uri.strip_prefix("rtp://") {
+            let encoding = self.input.encoding.clone().unwrap_or("H264".to_string());
+
+            format!("udpsrc uri=udp://{address} name=src caps=\"application/x-rtp, media=video, clock-rate=90000, encoding-name={encoding}\" ! rtpjitterbuffer latency=200")
+        } else if uri.starts_with("udp://") {
+            format!("udpsrc uri={uri} name=src")
+        } else if uri.starts_with("srt://") {
+            format!("srtsrc uri=\"{uri}\" name=src")
+        } else {
+            panic!("network input uri must start with rtp://, udp:// or srt://");
+        }
+    }
 
-        if self.input.is_test() {
-            // pattern=smpte
-            let pattern = self
+    /// Returns the screen capture source. The captured region starts at
+    /// (`screen_x`, `screen_y`) and has the input size (only in Linux, where
+    /// `display` can select a X11 display as ":99" of Xvfb)
+    fn get_pipeline_screen_src(&self, num_buffers: &str) -> String {
+        if cfg!(target_os = "linux") {
+            let display = self
                 .input
-                .pattern
+                .display
                 .clone()
-                .unwrap_or("mandelbrot".to_string());
+                .map(|s| format!(" display-name={}", s))
+                .unwrap_or_default();
+            let startx = self.input.screen_x.unwrap_or(0);
+            let starty = self.input.screen_y.unwrap_or(0);
+            let endx = startx + self.input.width as u32 - 1;
+            let endy = starty + self.input.height as u32 - 1;
 
-            format!("gltestsrc is-live=1 pattern={pattern} {num_buffers} name=src  ! video/x-raw(memory:GLMemory), framerate={framerate}, width={width}, height={height}, pixel-aspect-ratio=1/1 ! glcolorconvert ! gldownload {format}")
+            format!("ximagesrc{display} startx={startx} starty={starty} endx={endx} endy={endy} use-damage=0 {num_buffers} name=src")
+        } else if cfg!(target_os = "windows") {
+            format!("d3d11screencapturesrc {num_buffers} name=src ! d3d11download")
+        } else if cfg!(target_os = "macos") {
+            format!("avfvideosrc capture-screen=1 {num_buffers} name=src")
         } else {
-            let src = if cfg!(target_os = "linux") {
-                "v4l2src"
-            } else if cfg!(target_os = "windows") {
-                "mfvideosrc"
-            } else if cfg!(target_os = "macos") {
-                //hardcoded until gst::DeviceMonitor
-                return "avfvideosrc ! video/x-raw, width=1280, height=720, format=NV12, framerate=30/1 ! videoconvertscale ! videorate ".to_string();
-            } else {
-                unimplemented!()
-            };
-
-            format!("{src} {num_buffers} ! image/jpeg, width={width}, height={height}, framerate={framerate} ! jpegdec ! videoconvertscale ! videorate {format}")
+            unimplemented!()
+        }
+    }
+
+    pub fn get_pipeline_scale0(&self) -> String {
+        self.get_pipeline_scale(&self.encoder0)
+    }
+
+    pub fn get_pipeline_scale1(&self) -> String {
+        self.get_pipeline_scale(&self.encoder1)
+    }
+
+    /// Returns the scaling done before the encoder when it has its own resolution or framerate.
+    /// The queue runs it in the thread of the branch, not in the one of the source tee.
+    fn get_pipeline_scale(&self, enc: &Encoder) -> String {
+        if !enc.is_scaled() {
+            return "".to_string();
+        }
+
+        let width = enc.width.unwrap_or(self.input.width);
+        let height = enc.height.unwrap_or(self.input.height);
+        let framerate = enc.framerate.as_ref().unwrap_or(&self.input.framerate);
+
+        format!("queue ! videoconvertscale ! videorate ! video/x-raw, width={width}, height={height}, framerate={framerate}, pixel-aspect-ratio=1/1 ! ")
+    }
+
+    pub fn get_pipeline_upscale0(&self) -> String {
+        self.get_pipeline_upscale(&self.encoder0)
+    }
+
+    pub fn get_pipeline_upscale1(&self) -> String {
+        self.get_pipeline_upscale(&self.encoder1)
+    }
+
+    /// Returns the scaling done after the decoder to go back to the input resolution and framerate.
+    /// Only the framerate w/o upscale, the decoded video keeps the encoded resolution.
+    fn get_pipeline_upscale(&self, enc: &Encoder) -> String {
+        if !enc.is_scaled() {
+            return "".to_string();
+        }
+
+        let width = self.input.width;
+        let height = self.input.height;
+        let framerate = &self.input.framerate;
+
+        if !enc.upscale {
+            return format!("videorate ! video/x-raw, framerate={framerate} ! ");
         }
+
+        format!("videoconvertscale ! videorate ! video/x-raw, width={width}, height={height}, framerate={framerate}, pixel-aspect-ratio=1/1 ! ")
     }
 
     pub fn get_pipeline_enc0(&self) -> String {
@@ -237,6 +593,127 @@ impl Settings {
         }
     }
 
+    /// Returns the tee added after the encoder when its elementary stream is saved.
+    pub fn get_pipeline_save_tee0(&self) -> String {
+        get_pipeline_tee("save0", self.encoder0.save_path.is_some())
+    }
+
+    pub fn get_pipeline_save_tee1(&self) -> String {
+        get_pipeline_tee("save1", self.encoder1.save_path.is_some())
+    }
+
+    pub fn get_pipeline_save0(&self) -> String {
+        self.get_pipeline_save(&self.encoder0, "save0")
+    }
+
+    pub fn get_pipeline_save1(&self) -> String {
+        self.get_pipeline_save(&self.encoder1, "save1")
+    }
+
+    /// Returns the branch that saves the elementary stream of the encoder in `save_path`
+    /// using the file extension:
+    ///  * `.h264`, `.264`, `.h265`, `.265` or `.hevc` raw Annex-B
+    ///  * `.ivf` IVF for VP8, VP9 or AV1
+    ///  * `.mkv` Matroska, also for the raw video of `identity`
+    fn get_pipeline_save(&self, enc: &Encoder, name: &str) -> String {
+        let Some(location) = &enc.save_path else {
+            return "".to_string();
+        };
+
+        let parse = match enc.kind {
+            EncoderType::identity => "identity",
+            EncoderType::custom => "parsebin",
+            EncoderType::x264enc => "h264parse",
+            EncoderType::x265enc => "h265parse",
+            EncoderType::rav1enc => "av1parse",
+            EncoderType::h266enc => {
+                unimplemented!();
+            }
+        };
+
+        let extension = get_extension(location);
+
+        let format = match extension.as_str() {
+            "h264" | "264" => "video/x-h264, stream-format=byte-stream",
+            "h265" | "265" | "hevc" => "video/x-h265, stream-format=byte-stream",
+            "ivf" => "avmux_ivf",
+            "mkv" => "matroskamux",
+            _ => panic!("save_path must end with .h264, .264, .h265, .265, .hevc, .ivf or .mkv"),
+        };
+
+        // the container or the byte-stream caps must accept the stream of the encoder
+        let supported = match enc.kind {
+            EncoderType::identity => extension == "mkv",
+            EncoderType::custom => true,
+            EncoderType::x264enc => matches!(extension.as_str(), "h264" | "264" | "mkv"),
+            EncoderType::x265enc => matches!(extension.as_str(), "h265" | "265" | "hevc" | "mkv"),
+            EncoderType::rav1enc => matches!(extension.as_str(), "ivf" | "mkv"),
+            EncoderType::h266enc => unreachable!(),
+        };
+        if !supported {
+            panic!(
+                "save_path .{extension} can not store the stream of {:?}",
+                enc.kind
+            );
+        }
+
+        format!(
+            "{name}. ! queue ! {parse} ! {format} ! filesink location=\"{location}\" sync=false"
+        )
+    }
+
+    /// Returns the tee added after the decoder when the decoded frames are dumped.
+    pub fn get_pipeline_dump_tee0(&self) -> String {
+        get_pipeline_tee("dump0", self.output.dump_dir.is_some())
+    }
+
+    pub fn get_pipeline_dump_tee1(&self) -> String {
+        get_pipeline_tee("dump1", self.output.dump_dir.is_some())
+    }
+
+    /// Returns the branches that dump the original and the decoded frames as Y4M files
+    /// (`source.y4m`, `enc0.y4m` and `enc1.y4m`) inside `dump_dir`.
+    /// The paths use `/` (valid on Windows too), backslashes would be escapes in the pipeline.
+    pub fn get_pipeline_dump(&self) -> String {
+        let Some(dir) = &self.output.dump_dir else {
+            return "".to_string();
+        };
+        let dir = dir.replace('\\', "/");
+        let dir = dir.trim_end_matches('/');
+
+        [("tee_src", "source"), ("dump0", "enc0"), ("dump1", "enc1")]
+            .iter()
+            .map(|(tee, name)| {
+                format!(
+                    "{tee}. ! queue ! videoconvert ! y4menc ! filesink location=\"{dir}/{name}.y4m\" sync=false"
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n        ")
+    }
+
+    /// Returns the tee added after the decoder when the loupe is enabled.
+    pub fn get_pipeline_loupe_tee0(&self) -> String {
+        get_pipeline_tee("loupe0", self.loupe)
+    }
+
+    pub fn get_pipeline_loupe_tee1(&self) -> String {
+        get_pipeline_tee("loupe1", self.loupe)
+    }
+
+    /// Returns the branches of the loupe, linked to the mixer pads after the compared videos.
+    pub fn get_pipeline_loupe(&self) -> String {
+        if !self.loupe {
+            return "".to_string();
+        }
+
+        [
+            "loupe0. ! queue ! videocrop name=crop2 ! mix.sink_2",
+            "loupe1. ! queue ! videocrop name=crop3 ! mix.sink_3",
+        ]
+        .join("\n        ")
+    }
+
     pub fn get_enc0_name(&self) -> String {
         self.get_enc_name(&self.encoder0)
     }
@@ -245,9 +722,21 @@ impl Settings {
         self.get_enc_name(&self.encoder1)
     }
 
+    /// Returns the name of the encoder `index` (0 or 1) drawn over its video, w/o the quotes
+    /// of custom encoders that would end the text property of the textoverlay
+    pub fn get_enc_label(&self, index: usize) -> String {
+        let enc = if index == 0 {
+            &self.encoder0
+        } else {
+            &self.encoder1
+        };
+
+        self.get_enc_name(enc).replace('"', "'")
+    }
+
     fn get_enc_name(&self, enc: &Encoder) -> String {
         let bitrate = enc.bitrate;
-        match enc.kind {
+        let name = match enc.kind {
             EncoderType::identity => "identity".to_string(),
             EncoderType::custom => {
                 let c = enc.custom.clone().expect("custom encoder w/o custom value");
@@ -266,6 +755,15 @@ impl Settings {
             EncoderType::h266enc => {
                 unimplemented!();
             }
+        };
+
+        if enc.is_scaled() {
+            let width = enc.width.unwrap_or(self.input.width);
+            let height = enc.height.unwrap_or(self.input.height);
+            let (fps_n, _) = self.get_enc_framerate(enc);
+            format!("{name} {width}x{height}@{fps_n}")
+        } else {
+            name
         }
     }
 
@@ -306,48 +804,158 @@ impl Settings {
         "Consolas 10".to_string()
     }
 
+    /// Returns the size of the output window, the input size by default
+    pub fn get_output_size(&self) -> (i32, i32) {
+        (
+            self.output.width.unwrap_or(self.input.width),
+            self.output.height.unwrap_or(self.input.height),
+        )
+    }
+
     pub fn get_pipeline_sink(&self) -> String {
-        let width = self.input.width;
-        let height = self.input.height;
+        let (width, height) = self.get_output_size();
         let framerate = &self.input.framerate;
         let caps = format!("video/x-raw,framerate={framerate},width={width}, height={height}, pixel-aspect-ratio=1/1");
 
-        let videosink = if cfg!(target_os = "linux") {
-            "xvimagesink"
-        } else if cfg!(target_os = "windows") {
-            "d3d12videosink"
-        } else if cfg!(target_os = "macos") {
-            "osxvideosink"
-        } else {
-            unimplemented!()
+        let videosink = match self.output.sink {
+            VideoSinkType::auto => {
+                if cfg!(target_os = "linux") {
+                    "xvimagesink"
+                } else if cfg!(target_os = "windows") {
+                    "d3d12videosink"
+                } else if cfg!(target_os = "macos") {
+                    "osxvideosink"
+                } else {
+                    unimplemented!()
+                }
+            }
+            VideoSinkType::glimagesink => "glimagesink",
+            // Note: the env var GST_GTK4_WINDOW=1 is needed to open a window
+            VideoSinkType::gtk4paintablesink => "gtk4paintablesink",
         };
 
-        if self.nooutput {
-            format!("{caps} ! fakesink sync=false")
+        let sinks: Vec<String> = self
+            .get_outputs()
+            .iter()
+            .map(|output| match output {
+                OutputType::Display => format!("{videosink} sync=false"),
+                OutputType::Record => get_pipeline_record(
+                    self.output
+                        .record
+                        .as_ref()
+                        .expect("record output w/o record value"),
+                ),
+                OutputType::Stream => get_pipeline_stream(
+                    self.output
+                        .stream
+                        .as_ref()
+                        .expect("stream output w/o stream value"),
+                ),
+                OutputType::Rtsp => {
+                    format!("intervideosink channel={RTSP_CHANNEL} sync=false")
+                }
+                OutputType::Fake => "fakesink sync=false".to_string(),
+            })
+            .collect();
+
+        match sinks.len() {
+            0 => format!("{caps} ! fakesink sync=false"),
+            1 => format!("{caps} ! {}", sinks[0]),
+            _ => {
+                let branches: String = sinks
+                    .iter()
+                    .map(|sink| format!("\n        tee_out. ! queue ! {sink}"))
+                    .collect();
+                format!("{caps} ! tee name=tee_out{branches}")
+            }
+        }
+    }
+
+    /// Returns the configured outputs of the composited view
+    pub fn get_outputs(&self) -> Vec<OutputType> {
+        if let Some(sinks) = &self.output.sinks {
+            return sinks.clone();
+        }
+
+        let mut outputs = vec![];
+        if !self.nooutput && !self.batch {
+            outputs.push(OutputType::Display);
+        }
+        if self.output.record.is_some() {
+            outputs.push(OutputType::Record);
+        }
+        outputs
+    }
+
+    /// Returns the number of buffers of the source, 1000 by default in batch mode
+    pub fn get_num_buffers(&self) -> Option<u32> {
+        if self.batch {
+            Some(self.input.num_buffers.unwrap_or(BATCH_NUM_BUFFERS))
         } else {
-            format!("{caps} ! {videosink} sync=false")
+            self.input.num_buffers
+        }
+    }
+
+    /// Creates the `dump_dir` and `snapshot_dir` directories, filesink and the snapshots
+    /// do not create them
+    pub fn create_output_dirs(&self) -> std::io::Result<()> {
+        for dir in [&self.output.dump_dir, &self.output.snapshot_dir]
+            .into_iter()
+            .flatten()
+        {
+            std::fs::create_dir_all(dir)?;
         }
+
+        Ok(())
+    }
+
+    /// Returns the port of the RTSP server, 8554 by default
+    #[cfg(feature = "rtsp")]
+    pub fn get_rtsp_port(&self) -> u16 {
+        self.output.rtsp_port.unwrap_or(8554)
+    }
+
+    /// Whether the video sink posts key press navigation events
+    pub fn sink_supports_key_navigation(&self) -> bool {
+        !self.get_outputs().contains(&OutputType::Display)
+            || self.output.sink == VideoSinkType::auto
     }
 
     pub fn get_framerate(&self) -> (u64, u64) {
-        let parts: Vec<&str> = self.input.framerate.split('/').collect();
+        parse_framerate(&self.input.framerate)
+    }
 
-        if parts.len() == 2 {
-            if let (Ok(numerator), Ok(denominator)) =
-                (parts[0].parse::<u64>(), parts[1].parse::<u64>())
-            {
-                return (numerator, denominator);
-            }
-        }
+    pub fn get_enc0_framerate(&self) -> (u64, u64) {
+        self.get_enc_framerate(&self.encoder0)
+    }
+
+    pub fn get_enc1_framerate(&self) -> (u64, u64) {
+        self.get_enc_framerate(&self.encoder1)
+    }
+
+    fn get_enc_framerate(&self, enc: &Encoder) -> (u64, u64) {
+        parse_framerate(enc.framerate.as_ref().unwrap_or(&self.input.framerate))
+    }
+}
+
+fn parse_framerate(framerate: &str) -> (u64, u64) {
+    let parts: Vec<&str> = framerate.split('/').collect();
 
-        panic!("framerate format must be num/den as \"30/1\"");
+    if parts.len() == 2 {
+        if let (Ok(numerator), Ok(denominator)) = (parts[0].parse::<u64>(), parts[1].parse::<u64>())
+        {
+            return (numerator, denominator);
+        }
     }
+
+    panic!("framerate format must be num/den as \"30/1\"");
 }
 // TODO: do settings.rs GStreamer agnostic
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::compositor::{Compositor, Mode};
 
     #[test]
     fn test_get_framerate() {
@@ -363,4 +971,475 @@ mod tests {
         assert_eq!(fps_n, 30000, "framerate num");
         assert_eq!(fps_d, 1001, "framerate den");
     }
+
+    #[test]
+    fn test_get_pipeline_scale() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_pipeline_scale0(), "", "no scale");
+        assert_eq!(s.get_pipeline_upscale0(), "", "no upscale");
+        assert_eq!(s.get_enc0_framerate(), (30, 1), "input framerate");
+        assert_eq!(s.get_enc0_name(), "x264enc bitrate=256", "name");
+
+        s.encoder0.width = Some(640);
+        s.encoder0.height = Some(360);
+        s.encoder0.framerate = Some("15/1".to_string());
+        assert_eq!(
+            s.get_pipeline_scale0(),
+            "queue ! videoconvertscale ! videorate ! video/x-raw, width=640, height=360, framerate=15/1, pixel-aspect-ratio=1/1 ! ",
+            "scale"
+        );
+        assert_eq!(
+            s.get_pipeline_upscale0(),
+            "videoconvertscale ! videorate ! video/x-raw, width=1280, height=720, framerate=30/1, pixel-aspect-ratio=1/1 ! ",
+            "upscale"
+        );
+        assert_eq!(s.get_enc0_framerate(), (15, 1), "encoder framerate");
+        assert_eq!(s.get_enc0_name(), "x264enc bitrate=256 640x360@15", "name");
+
+        assert_eq!(s.get_pipeline_scale1(), "", "no scale");
+        assert_eq!(s.get_pipeline_upscale1(), "", "no upscale");
+        assert_eq!(s.get_enc1_framerate(), (30, 1), "input framerate");
+
+        s.encoder0.upscale = false;
+        assert_eq!(
+            s.get_pipeline_upscale0(),
+            "videorate ! video/x-raw, framerate=30/1 ! ",
+            "only framerate w/o upscale"
+        );
+    }
+
+    #[test]
+    fn test_get_pipeline_save() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_pipeline_save_tee0(), "", "no tee");
+        assert_eq!(s.get_pipeline_save0(), "", "no save");
+
+        s.encoder0.save_path = Some("enc0.h264".to_string());
+        s.encoder1.kind = EncoderType::rav1enc;
+        s.encoder1.save_path = Some("enc1.ivf".to_string());
+        assert_eq!(s.get_pipeline_save_tee0(), "tee name=save0 ! ", "tee");
+        assert_eq!(
+            s.get_pipeline_save0(),
+            "save0. ! queue ! h264parse ! video/x-h264, stream-format=byte-stream ! filesink location=\"enc0.h264\" sync=false",
+            "annex-b"
+        );
+        assert_eq!(s.get_pipeline_save_tee1(), "tee name=save1 ! ", "tee");
+        assert_eq!(
+            s.get_pipeline_save1(),
+            "save1. ! queue ! av1parse ! avmux_ivf ! filesink location=\"enc1.ivf\" sync=false",
+            "ivf"
+        );
+
+        s.encoder1.kind = EncoderType::x265enc;
+        s.encoder1.save_path = Some("enc1.mkv".to_string());
+        assert_eq!(
+            s.get_pipeline_save1(),
+            "save1. ! queue ! h265parse ! matroskamux ! filesink location=\"enc1.mkv\" sync=false",
+            "mkv"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pipeline_save_extension_of_other_codec() {
+        let mut s = Settings::default();
+        s.encoder0.kind = EncoderType::x265enc;
+        s.encoder0.save_path = Some("enc0.h264".to_string());
+        s.get_pipeline_save0();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pipeline_save_identity_raw() {
+        let mut s = Settings::default();
+        s.encoder0.kind = EncoderType::identity;
+        s.encoder0.save_path = Some("enc0.ivf".to_string());
+        s.get_pipeline_save0();
+    }
+
+    #[test]
+    fn test_get_pipeline_dump() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_pipeline_dump_tee0(), "", "no tee");
+        assert_eq!(s.get_pipeline_dump(), "", "no dump");
+
+        s.output.dump_dir = Some("/tmp/dump".to_string());
+        assert_eq!(s.get_pipeline_dump_tee0(), "tee name=dump0 ! ", "tee");
+        assert_eq!(s.get_pipeline_dump_tee1(), "tee name=dump1 ! ", "tee");
+
+        let dump = s.get_pipeline_dump();
+        assert!(
+            dump.contains("tee_src. ! queue ! videoconvert ! y4menc ! filesink location=\"/tmp/dump/source.y4m\""),
+            "source"
+        );
+        assert!(
+            dump.contains(
+                "dump0. ! queue ! videoconvert ! y4menc ! filesink location=\"/tmp/dump/enc0.y4m\""
+            ),
+            "enc0"
+        );
+        assert!(
+            dump.contains(
+                "dump1. ! queue ! videoconvert ! y4menc ! filesink location=\"/tmp/dump/enc1.y4m\""
+            ),
+            "enc1"
+        );
+
+        s.output.dump_dir = Some("C:\\Users\\me\\dump\\".to_string());
+        assert!(
+            s.get_pipeline_dump()
+                .contains("filesink location=\"C:/Users/me/dump/source.y4m\""),
+            "windows dir w/o backslashes"
+        );
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut compositor = Compositor::new_split(WIDTH, HEIGHT);
+        let mut bookmarks = BTreeMap::new();
+        compositor.zoom_in();
+        bookmarks.insert("1".to_string(), compositor.get_bookmark());
+        compositor.top_bottom_mode();
+        compositor.set_exact_zoom(4);
+        compositor.move_pos(-12, 34);
+        bookmarks.insert("2".to_string(), compositor.get_bookmark());
+
+        let path = std::env::temp_dir().join("codeccomp_test_bookmarks.toml");
+        let path = path.to_str().unwrap();
+        save_bookmarks(path, &bookmarks).unwrap();
+
+        let s: Settings = Config::builder()
+            .add_source(File::with_name(path))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(s.bookmarks, bookmarks, "bookmarks loaded");
+        assert_eq!(s.bookmarks["2"].mode, Mode::TopBottom, "mode");
+        assert_eq!(s.bookmarks["2"].exact_zoom, Some(4), "exact_zoom");
+        assert_eq!(s.bookmarks["1"].exact_zoom, None, "exact_zoom");
+    }
+
+    #[test]
+    fn test_get_pipeline_loupe() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_pipeline_loupe_tee0(), "", "no tee");
+        assert_eq!(s.get_pipeline_loupe(), "", "no loupe");
+
+        s.loupe = true;
+        assert_eq!(s.get_pipeline_loupe_tee0(), "tee name=loupe0 ! ", "tee");
+        assert_eq!(s.get_pipeline_loupe_tee1(), "tee name=loupe1 ! ", "tee");
+
+        let loupe = s.get_pipeline_loupe();
+        assert!(
+            loupe.contains("loupe0. ! queue ! videocrop name=crop2 ! mix.sink_2"),
+            "enc0"
+        );
+        assert!(
+            loupe.contains("loupe1. ! queue ! videocrop name=crop3 ! mix.sink_3"),
+            "enc1"
+        );
+    }
+
+    #[test]
+    fn test_get_enc_label() {
+        let mut s = Settings::default();
+        s.encoder1.kind = EncoderType::custom;
+        s.encoder1.custom = Some("a \"b\" c".to_string());
+
+        assert_eq!(s.get_enc_label(0), s.get_enc0_name(), "label0");
+        assert_eq!(s.get_enc_label(1), "c a 'b' c", "label1 w/o quotes");
+    }
+
+    #[test]
+    fn test_get_extension() {
+        assert_eq!(get_extension("dir.v2/out.MKV"), "mkv", "lowercase");
+        assert_eq!(get_extension("frames/frame_%05d.png"), "png", "pattern");
+    }
+
+    #[test]
+    fn test_get_pipeline_sink_record() {
+        let mut s = Settings::default();
+        assert!(!s.get_pipeline_sink().contains("tee_out"), "no tee");
+
+        s.output.record = Some("out.mkv".to_string());
+        let sink = s.get_pipeline_sink();
+        assert!(sink.contains("tee name=tee_out"), "tee");
+        assert!(
+            sink.contains("tee_out. ! queue ! videoconvert ! video/x-raw, format=I420 ! x264enc"),
+            "record branch in I420"
+        );
+        assert!(
+            sink.contains("matroskamux ! filesink location=\"out.mkv\""),
+            "mkv"
+        );
+
+        s.nooutput = true;
+        s.output.record = Some("out.mp4".to_string());
+        let sink = s.get_pipeline_sink();
+        assert!(!sink.contains("tee_out"), "only record, no tee");
+        assert!(!sink.contains("fakesink"), "only record, no fakesink");
+        assert!(
+            sink.contains("mp4mux ! filesink location=\"out.mp4\""),
+            "mp4"
+        );
+        assert!(
+            sink.contains("format=I420 ! x264enc"),
+            "only record in I420"
+        );
+    }
+
+    #[test]
+    fn test_get_output_size() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_output_size(), (1280, 720), "input size");
+        assert!(s.get_pipeline_sink().contains("width=1280, height=720"));
+
+        s.output.width = Some(3840);
+        s.output.height = Some(2160);
+        assert_eq!(s.get_output_size(), (3840, 2160), "output size");
+        assert!(s.get_pipeline_sink().contains("width=3840, height=2160"));
+    }
+
+    #[test]
+    fn test_get_pipeline_sink_type() {
+        let mut s = Settings::default();
+        assert!(s.sink_supports_key_navigation(), "default sink");
+
+        s.output.sink = VideoSinkType::glimagesink;
+        assert!(
+            s.get_pipeline_sink().ends_with("! glimagesink sync=false"),
+            "glimagesink"
+        );
+        assert!(!s.sink_supports_key_navigation(), "glimagesink");
+
+        s.output.sink = VideoSinkType::gtk4paintablesink;
+        assert!(
+            s.get_pipeline_sink()
+                .ends_with("! gtk4paintablesink sync=false"),
+            "gtk4paintablesink"
+        );
+        assert!(!s.sink_supports_key_navigation(), "gtk4paintablesink");
+    }
+
+    #[test]
+    fn test_get_pipeline_sink_outputs() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_outputs(), vec![OutputType::Display], "default");
+
+        s.nooutput = true;
+        assert!(s.get_outputs().is_empty(), "nooutput");
+        assert!(s.get_pipeline_sink().ends_with("! fakesink sync=false"));
+
+        s.output.sinks = Some(vec![
+            OutputType::Display,
+            OutputType::Record,
+            OutputType::Stream,
+            OutputType::Fake,
+        ]);
+        s.output.record = Some("out.mkv".to_string());
+        s.output.stream = Some("rtp://127.0.0.1:5004".to_string());
+        let sink = s.get_pipeline_sink();
+        assert!(sink.contains("tee name=tee_out"), "tee");
+        assert_eq!(sink.matches("tee_out. ! queue !").count(), 4, "branches");
+        assert!(sink.contains("filesink location=\"out.mkv\""), "record");
+        assert!(
+            sink.contains("rtph264pay ! udpsink host=127.0.0.1 port=5004"),
+            "rtp stream"
+        );
+        assert_eq!(sink.matches("format=I420 ! x264enc").count(), 2, "I420");
+        assert!(sink.contains("tee_out. ! queue ! fakesink"), "fake");
+
+        s.output.sinks = Some(vec![OutputType::Rtsp]);
+        assert_eq!(
+            s.get_pipeline_sink(),
+            "video/x-raw,framerate=30/1,width=1280, height=720, pixel-aspect-ratio=1/1 ! intervideosink channel=codeccomp sync=false",
+            "rtsp"
+        );
+
+        s.output.sinks = Some(vec![OutputType::Stream]);
+        s.output.stream = Some("srt://:7002?mode=listener".to_string());
+        let sink = s.get_pipeline_sink();
+        assert!(!sink.contains("tee_out"), "no tee");
+        assert!(
+            sink.contains("mpegtsmux ! srtsink uri=\"srt://:7002?mode=listener\""),
+            "srt stream"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pipeline_sink_record_unknown_extension() {
+        let mut s = Settings::default();
+        s.output.record = Some("out.avi".to_string());
+        s.get_pipeline_sink();
+    }
+
+    #[test]
+    fn test_batch() {
+        let mut s = Settings::default();
+        assert!(s.get_pipeline_src().starts_with("gltestsrc is-live=1"));
+        assert_eq!(s.get_num_buffers(), None, "num_buffers");
+        assert_eq!(s.get_outputs(), vec![OutputType::Display], "display");
+
+        s.batch = true;
+        let src = s.get_pipeline_src();
+        assert!(src.starts_with("gltestsrc is-live=0"), "not live");
+        assert!(src.contains("num-buffers=1000"), "default num-buffers");
+        assert!(s.get_outputs().is_empty(), "no display");
+        assert!(s.get_pipeline_sink().ends_with("! fakesink sync=false"));
+
+        s.input.num_buffers = Some(10);
+        assert_eq!(s.get_num_buffers(), Some(10), "num_buffers");
+
+        s.input.input = InputType::Stress;
+        assert!(
+            s.get_pipeline_src().contains("identity sync=0"),
+            "no pacing"
+        );
+    }
+
+    #[test]
+    fn test_get_pipeline_src_image_sequence() {
+        let mut s = Settings::default();
+        s.input.input = InputType::ImageSequence;
+        s.input.location = Some("frames/frame_%05d.png".to_string());
+        s.input.num_buffers = Some(100);
+        let src = s.get_pipeline_src();
+
+        assert!(src.starts_with("multifilesrc location=\"frames/frame_%05d.png\""));
+        assert!(src.contains("stop-index=99"), "stop-index");
+        assert!(src.contains("image/png, framerate=30/1"), "caps");
+        assert!(src.contains("! pngdec !"), "decoder");
+
+        s.input.location = Some("frames/frame_%05d.TIFF".to_string());
+        assert!(s.get_pipeline_src().contains("! avdec_tiff !"), "decoder");
+
+        s.input.location = Some("frames/frame_%05d.jpg".to_string());
+        assert!(s.get_pipeline_src().contains("! jpegdec !"), "decoder");
+    }
+
+    #[test]
+    fn test_get_pipeline_src_network() {
+        let mut s = Settings::default();
+        s.input.input = InputType::Network;
+
+        s.input.uri = Some("rtp://127.0.0.1:5000".to_string());
+        let src = s.get_pipeline_src();
+        assert!(src.starts_with("udpsrc uri=udp://127.0.0.1:5000 "), "rtp");
+        assert!(src.contains("encoding-name=H264"), "rtp encoding");
+
+        s.input.encoding = Some("H265".to_string());
+        assert!(
+            s.get_pipeline_src().contains("encoding-name=H265"),
+            "rtp encoding"
+        );
+
+        s.input.uri = Some("udp://0.0.0.0:5000".to_string());
+        let src = s.get_pipeline_src();
+        assert!(
+            src.starts_with("udpsrc uri=udp://0.0.0.0:5000 name=src"),
+            "udp"
+        );
+
+        s.input.uri = Some("srt://:7001?mode=listener".to_string());
+        let src = s.get_pipeline_src();
+        assert!(
+            src.starts_with("srtsrc uri=\"srt://:7001?mode=listener\" name=src"),
+            "srt"
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_get_pipeline_src_screen() {
+        let mut s = Settings::default();
+        s.input.input = InputType::Screen;
+        let src = s.get_pipeline_src();
+        assert!(
+            src.starts_with("ximagesrc startx=0 starty=0 endx=1279 endy=719 use-damage=0"),
+            "ximagesrc full region"
+        );
+
+        s.input.display = Some(":99".to_string());
+        s.input.screen_x = Some(10);
+        s.input.screen_y = Some(20);
+        s.input.width = 640;
+        s.input.height = 360;
+        let src = s.get_pipeline_src();
+        assert!(
+            src.starts_with(
+                "ximagesrc display-name=:99 startx=10 starty=20 endx=649 endy=379 use-damage=0"
+            ),
+            "ximagesrc region"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pipeline_src_network_unknown_scheme() {
+        let mut s = Settings::default();
+        s.input.input = InputType::Network;
+        s.input.uri = Some("http://127.0.0.1:8080".to_string());
+        s.get_pipeline_src();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pipeline_src_image_sequence_unknown_extension() {
+        let mut s = Settings::default();
+        s.input.input = InputType::ImageSequence;
+        s.input.location = Some("frames/frame_%05d.bmp".to_string());
+        s.get_pipeline_src();
+    }
+
+    #[test]
+    fn test_create_output_dirs() {
+        let root = std::env::temp_dir().join(format!("codeccomp_dirs_{}", std::process::id()));
+        let mut s = Settings::default();
+        s.output.dump_dir = Some(root.join("dump").to_string_lossy().to_string());
+        s.output.snapshot_dir = Some(root.join("a/snapshots").to_string_lossy().to_string());
+
+        s.create_output_dirs().unwrap();
+        assert!(root.join("dump").is_dir(), "dump_dir created");
+        assert!(
+            root.join("a/snapshots").is_dir(),
+            "nested snapshot_dir created"
+        );
+        s.create_output_dirs().expect("existing directories");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "rtsp")]
+    fn test_rtsp_port() {
+        let mut s = Settings::default();
+        assert_eq!(s.get_rtsp_port(), 8554, "default rtsp port");
+
+        s.output.rtsp_port = Some(9554);
+        assert_eq!(s.get_rtsp_port(), 9554, "rtsp port");
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        let mut s = Settings::default();
+        s.input.input = InputType::Camera;
+        s.output.sinks = Some(vec![OutputType::Display]);
+        assert!(s.validate().is_ok(), "not batch");
+
+        s.batch = true;
+        assert!(s.validate().is_err(), "live input");
+
+        s.input.input = InputType::Stress;
+        assert!(s.validate().is_err(), "display");
+
+        s.output.sinks = Some(vec![OutputType::Record, OutputType::Fake]);
+        assert!(s.validate().is_ok(), "record");
+
+        s.output.sinks = None;
+        assert!(s.validate().is_ok(), "default outputs");
+    }
 }
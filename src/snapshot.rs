@@ -0,0 +1,237 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use gst::prelude::*;
+
+use crate::Settings;
+
+const VIEW: usize = 0;
+const SOURCE: usize = 1;
+const ENC0: usize = 2;
+const ENC1: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Waiting for the next source frame
+    Requested,
+    /// Waiting for the decoded frames and the view of the source frame with this PTS,
+    /// the source is ahead of them by the latency of the queues, encoders and decoders
+    Capturing(Option<gst::ClockTime>),
+}
+
+/// Frames captured on demand: the composited view (with metrics), the original source and
+/// the decoded frames (at the encoded resolution), all of the same source frame.
+pub struct Snapshot {
+    dir: String,
+    names: [String; 4],
+    fps_n: u64,
+    fps_d: u64,
+    state: State,
+    // Only the captured frames are kept to avoid starving the buffer pools
+    samples: [Option<gst::Sample>; 4],
+}
+
+impl Snapshot {
+    pub fn new(s: &Settings) -> Self {
+        let (fps_n, fps_d) = s.get_framerate();
+
+        Self {
+            dir: s.output.snapshot_dir.clone().unwrap_or(".".to_string()),
+            names: [
+                "view".to_string(),
+                "source".to_string(),
+                format!("enc0_{}", s.get_enc0_name()),
+                format!("enc1_{}", s.get_enc1_name()),
+            ],
+            fps_n,
+            fps_d,
+            state: State::Idle,
+            samples: Default::default(),
+        }
+    }
+
+    /// Captures the next source frame and the decoded frames and view of it, saved as PNG
+    /// files once all of them are captured.
+    pub fn save(&mut self) {
+        if self.state == State::Idle {
+            self.state = State::Requested;
+        } else {
+            println!("snapshot in progress");
+        }
+    }
+
+    /// Whether the frame with `pts` seen in the pad of `index` has to be captured
+    fn wants(&self, index: usize, pts: Option<gst::ClockTime>) -> bool {
+        match self.state {
+            State::Idle => false,
+            State::Requested => index == SOURCE,
+            State::Capturing(target) => {
+                index != SOURCE
+                    && self.samples[index].is_none()
+                    && match (target, pts) {
+                        (Some(target), Some(pts)) => pts >= target,
+                        _ => true,
+                    }
+            }
+        }
+    }
+
+    fn capture(&mut self, index: usize, sample: gst::Sample) {
+        if index == SOURCE {
+            self.state = State::Capturing(get_pts(&sample));
+        }
+        self.samples[index] = Some(sample);
+
+        if self.samples.iter().all(Option::is_some) {
+            self.write();
+            self.state = State::Idle;
+        }
+    }
+
+    /// Writes the captured frames named with the frame number of each sample and the
+    /// encoder names.
+    fn write(&mut self) {
+        let files: Vec<(PathBuf, gst::Sample)> = std::mem::take(&mut self.samples)
+            .into_iter()
+            .zip(self.names.iter())
+            .filter_map(|(sample, name)| {
+                let sample = sample?;
+                let frame = get_pts(&sample)
+                    .and_then(|pts| get_frame(pts, self.fps_n, self.fps_d))
+                    .unwrap_or_default();
+                let file = get_file_name(frame, name);
+                Some((Path::new(&self.dir).join(file), sample))
+            })
+            .collect();
+
+        // PNG encoding is slow, not done in the streaming threads
+        std::thread::spawn(move || {
+            for (path, sample) in files {
+                if let Err(err) = save_png(&path, &sample) {
+                    println!("Error saving snapshot {}: {err}", path.display());
+                } else {
+                    println!("snapshot saved {}", path.display());
+                }
+            }
+        });
+    }
+}
+
+fn get_pts(sample: &gst::Sample) -> Option<gst::ClockTime> {
+    sample.buffer().and_then(|b| b.pts())
+}
+
+fn get_frame(pts: gst::ClockTime, fps_n: u64, fps_d: u64) -> Option<u64> {
+    pts.nseconds().mul_div_floor(fps_n, fps_d * 1_000_000_000)
+}
+
+/// File name without the characters of the encoder name not valid in file names
+fn get_file_name(frame: u64, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("snapshot_{frame:06}_{name}.png")
+}
+
+fn save_png(path: &Path, sample: &gst::Sample) -> Result<(), anyhow::Error> {
+    let caps = gst::Caps::builder("image/png").build();
+    let png = gst_video::convert_sample(sample, &caps, gst::ClockTime::from_seconds(5))?;
+    let buffer = png
+        .buffer()
+        .ok_or_else(|| anyhow::anyhow!("sample w/o buffer"))?;
+    let map = buffer.map_readable()?;
+    std::fs::write(path, map.as_slice())?;
+
+    Ok(())
+}
+
+pub fn add_probe(pipeline: &gst::Pipeline, snapshot: Arc<Mutex<Snapshot>>) {
+    let pads = [
+        (VIEW, "metrics", "src"),
+        (SOURCE, "tee_src", "sink"),
+        (ENC0, "decoded0", "src"),
+        (ENC1, "decoded1", "src"),
+    ];
+
+    for (index, element, pad) in pads {
+        let pad = pipeline.by_name(element).unwrap().static_pad(pad).unwrap();
+        let snapshot = snapshot.clone();
+
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, probe_info| {
+            let Some(buffer) = probe_info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let mut snapshot = snapshot.lock().unwrap();
+            if !snapshot.wants(index, buffer.pts()) {
+                return gst::PadProbeReturn::Ok;
+            }
+            let Some(caps) = pad.current_caps() else {
+                return gst::PadProbeReturn::Ok;
+            };
+
+            let sample = gst::Sample::builder().buffer(buffer).caps(&caps).build();
+            snapshot.capture(index, sample);
+
+            gst::PadProbeReturn::Ok
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_file_name() {
+        assert_eq!(
+            get_file_name(42, "enc0_x264enc bitrate=256"),
+            "snapshot_000042_enc0_x264enc_bitrate_256.png"
+        );
+        assert_eq!(get_file_name(1234567, "view"), "snapshot_1234567_view.png");
+    }
+
+    #[test]
+    fn test_wants() {
+        let ms = gst::ClockTime::from_mseconds;
+        let mut snapshot = Snapshot::new(&Settings::default());
+        assert!(!snapshot.wants(SOURCE, Some(ms(0))), "idle");
+        assert!(!snapshot.wants(ENC0, Some(ms(0))), "idle");
+
+        snapshot.save();
+        assert!(snapshot.wants(SOURCE, Some(ms(100))), "next source frame");
+        assert!(
+            !snapshot.wants(ENC0, Some(ms(0))),
+            "decoded before the source"
+        );
+
+        snapshot.state = State::Capturing(Some(ms(100)));
+        assert!(!snapshot.wants(SOURCE, Some(ms(133))), "source captured");
+        assert!(!snapshot.wants(ENC0, Some(ms(66))), "older decoded frame");
+        assert!(
+            snapshot.wants(ENC0, Some(ms(100))),
+            "decoded frame of the source"
+        );
+        assert!(
+            snapshot.wants(ENC1, Some(ms(133))),
+            "first decoded frame after it"
+        );
+        assert!(snapshot.wants(VIEW, None), "view w/o pts");
+
+        snapshot.save();
+        assert_eq!(
+            snapshot.state,
+            State::Capturing(Some(ms(100))),
+            "capture in progress"
+        );
+    }
+
+    #[test]
+    fn test_get_frame() {
+        let pts = gst::ClockTime::from_mseconds(1000);
+        assert_eq!(get_frame(pts, 30, 1), Some(30), "30 fps");
+        assert_eq!(get_frame(pts, 30000, 1001), Some(29), "29.97 fps");
+    }
+}
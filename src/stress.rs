@@ -0,0 +1,308 @@
+//! Synthetic sources with codec stress scenes. All the content is a function of
+//! the seed and the frame number, so the same seed always generates the same video.
+use std::sync::Mutex;
+
+use gst::prelude::*;
+
+use crate::settings::StressScene;
+use crate::Settings;
+
+const GLYPH_WIDTH: i64 = 8;
+const GLYPH_HEIGHT: i64 = 12;
+const PAN_X_STEP: i64 = 16;
+const PAN_Y_STEP: i64 = 4;
+const CELL_SIZE: i64 = 16;
+const CUT_INTERVAL: u64 = 15;
+const FLASH_INTERVAL: u64 = 4;
+const GRAIN_AMPLITUDE: i64 = 24;
+
+/// splitmix64
+fn hash(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn hash3(seed: u64, a: u64, b: u64) -> u64 {
+    hash(seed ^ hash(a ^ hash(b)))
+}
+
+pub struct StressSource {
+    scene: StressScene,
+    seed: u64,
+    width: i64,
+    height: i64,
+    pub frame: u64,
+}
+
+impl StressSource {
+    pub fn new(scene: StressScene, seed: u64, width: i32, height: i32) -> Self {
+        Self {
+            scene,
+            seed,
+            width: width as i64,
+            height: height as i64,
+            frame: 0,
+        }
+    }
+
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Renders the plane (0 Y, 1 U and 2 V of I420) of the current frame
+    pub fn fill_plane(&self, plane: usize, data: &mut [u8], stride: usize) {
+        let (width, height) = if plane == 0 {
+            (self.width, self.height)
+        } else {
+            ((self.width + 1) / 2, (self.height + 1) / 2)
+        };
+
+        for y in 0..height {
+            let row = &mut data[y as usize * stride..];
+            for x in 0..width {
+                row[x as usize] = if plane == 0 {
+                    self.luma(x, y)
+                } else {
+                    self.chroma(plane, x, y)
+                };
+            }
+        }
+    }
+
+    /// Blocky texture with fine detail, used as background of the motion scenes
+    fn texture(&self, seed: u64, x: i64, y: i64) -> i64 {
+        let cell = hash3(
+            seed,
+            x.div_euclid(CELL_SIZE) as u64,
+            y.div_euclid(CELL_SIZE) as u64,
+        );
+        let detail = if (x + y).rem_euclid(2) == 0 { 12 } else { 0 };
+        32 + (cell % 180) as i64 + detail
+    }
+
+    fn luma(&self, x: i64, y: i64) -> u8 {
+        let frame = self.frame as i64;
+        let value = match self.scene {
+            StressScene::ScrollingText => {
+                // One pixel per frame to stress the sub-block motion estimation
+                let y = y + frame;
+                let (row, gy) = (y.div_euclid(GLYPH_HEIGHT), y.rem_euclid(GLYPH_HEIGHT));
+                let (col, gx) = (x.div_euclid(GLYPH_WIDTH), x.rem_euclid(GLYPH_WIDTH));
+                let glyph = hash3(self.seed, row as u64, col as u64) % 64;
+                // 5x7 glyph inside the cell, some glyphs are spaces between words
+                let inside = (1..6).contains(&gx) && (2..9).contains(&gy) && glyph >= 8;
+                if inside && (hash3(self.seed, glyph, 0) >> ((gx - 1) + (gy - 2) * 5)) & 1 == 1 {
+                    16
+                } else {
+                    235
+                }
+            }
+            StressScene::FilmGrain => {
+                let base = 64 + (x + y) * 128 / (self.width + self.height);
+                let noise = hash3(self.seed ^ self.frame, x as u64, y as u64)
+                    % (2 * GRAIN_AMPLITUDE as u64 + 1);
+                base + noise as i64 - GRAIN_AMPLITUDE
+            }
+            StressScene::FastPan => {
+                self.texture(self.seed, x + frame * PAN_X_STEP, y + frame * PAN_Y_STEP)
+            }
+            StressScene::SceneCuts => {
+                let shot = self.frame / CUT_INTERVAL;
+                let brightness = (hash3(self.seed, shot, 1) % 96) as i64 - 48;
+                self.texture(self.seed ^ hash(shot), x + frame, y) + brightness
+            }
+            StressScene::Gradient => {
+                // Narrow luma range in a slowly moving sky-like gradient to show banding
+                60 + (y + frame / 2).rem_euclid(self.height) * 100 / self.height
+            }
+            StressScene::Flashing => {
+                let value = self.texture(self.seed, x, y);
+                if (self.frame / FLASH_INTERVAL).is_multiple_of(2) {
+                    value
+                } else {
+                    16 + value / 4
+                }
+            }
+        };
+
+        value.clamp(0, 255) as u8
+    }
+
+    fn chroma(&self, plane: usize, x: i64, y: i64) -> u8 {
+        let sign = if plane == 1 { 1 } else { -1 };
+        let value = match self.scene {
+            StressScene::ScrollingText => 128,
+            StressScene::FilmGrain => {
+                let noise = hash3(self.seed ^ self.frame, plane as u64, (y << 32 | x) as u64) % 9;
+                124 + noise as i64
+            }
+            StressScene::FastPan | StressScene::Flashing => {
+                let frame = if self.scene == StressScene::FastPan {
+                    self.frame as i64
+                } else {
+                    0
+                };
+                let x = x + frame * PAN_X_STEP / 2;
+                let y = y + frame * PAN_Y_STEP / 2;
+                let cell = hash3(
+                    self.seed ^ plane as u64,
+                    x.div_euclid(CELL_SIZE) as u64,
+                    y.div_euclid(CELL_SIZE) as u64,
+                );
+                96 + (cell % 64) as i64
+            }
+            StressScene::SceneCuts => {
+                let shot = self.frame / CUT_INTERVAL;
+                64 + (hash3(self.seed, shot, plane as u64) % 128) as i64
+            }
+            StressScene::Gradient => {
+                let height = (self.height + 1) / 2;
+                128 + sign * (y * 40 / height)
+            }
+        };
+
+        value.clamp(0, 255) as u8
+    }
+}
+
+/// Feeds the `appsrc` named `src` with the configured stress scene
+pub fn add_source(pipeline: &gst::Pipeline, settings: &Settings) {
+    let appsrc = pipeline.by_name("src").unwrap();
+
+    let (fps_n, fps_d) = settings.get_framerate();
+    let info = gst_video::VideoInfo::builder(
+        gst_video::VideoFormat::I420,
+        settings.input.width as u32,
+        settings.input.height as u32,
+    )
+    .fps(gst::Fraction::new(fps_n as i32, fps_d as i32))
+    .build()
+    .unwrap();
+
+    let source = Mutex::new(StressSource::new(
+        settings.input.scene,
+        settings.input.seed,
+        settings.input.width,
+        settings.input.height,
+    ));
+    let num_buffers = settings.get_num_buffers();
+
+    appsrc.connect("need-data", false, move |args| {
+        let appsrc = args[0].get::<gst::Element>().unwrap();
+        let mut source = source.lock().unwrap();
+
+        if num_buffers.is_some_and(|n| source.frame >= n as u64) {
+            let _ = appsrc.emit_by_name::<gst::FlowReturn>("end-of-stream", &[]);
+            return None;
+        }
+
+        let mut buffer = gst::Buffer::with_size(info.size()).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            let pts = gst::ClockTime::SECOND.mul_div_floor(source.frame * fps_d, fps_n);
+            let next_pts = gst::ClockTime::SECOND.mul_div_floor((source.frame + 1) * fps_d, fps_n);
+            buffer.set_pts(pts);
+            buffer.set_duration(next_pts.zip(pts).map(|(next, pts)| next - pts));
+
+            let mut vframe =
+                gst_video::VideoFrameRef::from_buffer_ref_writable(buffer, &info).unwrap();
+            for plane in 0..3 {
+                let stride = vframe.plane_stride()[plane] as usize;
+                let data = vframe.plane_data_mut(plane as u32).unwrap();
+                source.fill_plane(plane, data, stride);
+            }
+        }
+        source.next_frame();
+
+        let _ = appsrc.emit_by_name::<gst::FlowReturn>("push-buffer", &[&buffer]);
+        None
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: i32 = 64;
+    const HEIGHT: i32 = 48;
+    const SCENES: [StressScene; 6] = [
+        StressScene::ScrollingText,
+        StressScene::FilmGrain,
+        StressScene::FastPan,
+        StressScene::SceneCuts,
+        StressScene::Gradient,
+        StressScene::Flashing,
+    ];
+
+    fn render(source: &StressSource) -> Vec<u8> {
+        let y_size = (WIDTH * HEIGHT) as usize;
+        let c_stride = (WIDTH / 2) as usize;
+        let c_size = c_stride * (HEIGHT / 2) as usize;
+
+        let mut data = vec![0; y_size + 2 * c_size];
+        let (y, uv) = data.split_at_mut(y_size);
+        let (u, v) = uv.split_at_mut(c_size);
+        source.fill_plane(0, y, WIDTH as usize);
+        source.fill_plane(1, u, c_stride);
+        source.fill_plane(2, v, c_stride);
+        data
+    }
+
+    #[test]
+    fn test_same_seed_same_frames() {
+        for scene in SCENES {
+            let mut a = StressSource::new(scene, 7, WIDTH, HEIGHT);
+            let mut b = StressSource::new(scene, 7, WIDTH, HEIGHT);
+
+            for _ in 0..20 {
+                assert_eq!(render(&a), render(&b), "{scene:?} frame {}", a.frame);
+                a.next_frame();
+                b.next_frame();
+            }
+        }
+    }
+
+    #[test]
+    fn test_different_seed_different_frames() {
+        for scene in SCENES {
+            if scene == StressScene::Gradient {
+                // gradient does not depend on the seed
+                continue;
+            }
+            let a = StressSource::new(scene, 1, WIDTH, HEIGHT);
+            let b = StressSource::new(scene, 2, WIDTH, HEIGHT);
+
+            assert_ne!(render(&a), render(&b), "{scene:?}");
+        }
+    }
+
+    #[test]
+    fn test_motion() {
+        for scene in SCENES {
+            let mut source = StressSource::new(scene, 3, WIDTH, HEIGHT);
+            let first = render(&source);
+            for _ in 0..CUT_INTERVAL {
+                source.next_frame();
+            }
+
+            assert_ne!(first, render(&source), "{scene:?}");
+        }
+    }
+
+    #[test]
+    fn test_scrolling_text_one_pixel_per_frame() {
+        let mut source = StressSource::new(StressScene::ScrollingText, 3, WIDTH, HEIGHT);
+        let first = render(&source);
+        source.next_frame();
+        let second = render(&source);
+
+        let line = WIDTH as usize;
+        assert_eq!(
+            first[line..(HEIGHT as usize) * line],
+            second[..(HEIGHT as usize - 1) * line],
+            "luma moved up one line"
+        );
+    }
+}
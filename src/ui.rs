@@ -1,10 +1,14 @@
 ///  * No UI (Only Gstreamer NavigationEvent)
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 use gst::prelude::*;
-use gst_video::NavigationEvent;
+use gst_video::{NavigationEvent, NavigationModifierType};
 
+use crate::metrics::Metrics;
 use crate::pipeline;
+use crate::settings;
+use crate::snapshot::Snapshot;
 use crate::Compositor;
 use crate::Settings;
 
@@ -15,31 +19,30 @@ pub struct MouseState {
     clicked_y: f64,
     clicked_xpos: i32,
     clicked_ypos: i32,
+    /// The inset of the picture_in_picture mode is dragged instead of the view
+    clicked_inset: bool,
 }
 
 pub fn add_probe(
     pipeline: &gst::Pipeline,
     state: Arc<Mutex<MouseState>>,
     compositor: Arc<Mutex<Compositor>>,
+    snapshot: Arc<Mutex<Snapshot>>,
+    metrics: Arc<Mutex<Metrics>>,
     settings: &Settings,
 ) {
-    let compositor_supports_crop: bool = settings.gst_pipeline_compositor_supports_crop();
-
-    let mixer = pipeline.by_name("mix").unwrap();
-    let crop0 = pipeline.by_name("crop0").unwrap();
-    let crop1 = pipeline.by_name("crop1").unwrap();
-    let mixer_src_pad = mixer.static_pad("src").unwrap();
-    let mixer_sink_0_pad = mixer.static_pad("sink_0").unwrap();
-    let mixer_sink_1_pad = mixer.static_pad("sink_1").unwrap();
-
-    pipeline::update_mixer(
-        &compositor.lock().unwrap(),
-        &mixer_sink_0_pad,
-        &mixer_sink_1_pad,
-        &crop0,
-        &crop1,
-        compositor_supports_crop,
-    );
+    let loupe_enabled = settings.loupe;
+    let metrics_overlay = settings
+        .metrics
+        .then(|| pipeline.by_name("metrics").unwrap());
+    let bookmarks = Mutex::new(settings.bookmarks.clone());
+    let mixer_src_pad = pipeline.by_name("mix").unwrap().static_pad("src").unwrap();
+    let mixer = pipeline::Mixer::new(pipeline, settings);
+
+    mixer.update(&compositor.lock().unwrap());
+
+    add_flicker_probe(&mixer_src_pad, compositor.clone(), mixer.clone(), settings);
+    add_caps_probes(pipeline, compositor.clone(), mixer.clone());
 
     // Probe added in the sink pad to get direct navigation events w/o transformation done by the zoom_mixer
     mixer_src_pad.add_probe(gst::PadProbeType::EVENT_UPSTREAM, move |_, probe_info| {
@@ -59,7 +62,29 @@ pub fn add_probe(
         let original_compositor = *compositor;
 
         match nav_event {
-            NavigationEvent::KeyPress { key, .. } => match key.as_str() {
+            NavigationEvent::KeyPress {
+                key,
+                modifier_state,
+                ..
+            } => match key.as_str() {
+                "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9"
+                    if modifier_state.contains(NavigationModifierType::CONTROL_MASK) =>
+                {
+                    let mut bookmarks = bookmarks.lock().unwrap();
+                    bookmarks.insert(key.clone(), compositor.get_bookmark());
+                    match settings::save_bookmarks(settings::BOOKMARKS_FILE, &bookmarks) {
+                        Ok(_) => println!("bookmark {key} saved"),
+                        Err(err) => println!("Error saving bookmark {key}: {err}"),
+                    }
+                }
+                "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9"
+                    if modifier_state.contains(NavigationModifierType::MOD1_MASK) =>
+                {
+                    match bookmarks.lock().unwrap().get(&key) {
+                        Some(bookmark) => compositor.set_bookmark(bookmark),
+                        None => println!("no bookmark {key}"),
+                    }
+                }
                 "Left" | "FLECHA IZQUIERDA" => {
                     compositor.move_pos(-10, 0);
                 }
@@ -101,22 +126,98 @@ pub fn add_probe(
                     compositor.side_by_side_mode();
                 }
                 "5" => {
-                    compositor.split_mode();
+                    if !compositor.is_top_bottom_mode() {
+                        compositor.split_mode();
+                    }
                     compositor.move_border(-10);
                 }
                 "6" => {
-                    compositor.split_mode();
+                    if !compositor.is_top_bottom_mode() {
+                        compositor.split_mode();
+                    }
                     compositor.move_border(10);
                 }
+                "7" => {
+                    compositor.top_bottom_mode();
+                }
+                "8" => {
+                    compositor.picture_in_picture_mode();
+                }
+                "9" => {
+                    compositor.flicker_mode();
+                }
+                "space" => {
+                    compositor.hold_flicker(true);
+                }
+                "F1" => {
+                    compositor.set_exact_zoom(1);
+                }
+                "F2" => {
+                    compositor.set_exact_zoom(2);
+                }
+                "F3" => {
+                    compositor.set_exact_zoom(4);
+                }
+                "F4" => {
+                    compositor.set_exact_zoom(8);
+                }
+                "x" => {
+                    compositor.swap();
+                    let mut metrics = metrics.lock().unwrap();
+                    metrics.swapped = compositor.swapped;
+                    // the overlay is only refreshed every second, the columns follow the labels now
+                    if let Some(overlay) = &metrics_overlay {
+                        overlay.set_property("text", metrics.to_string());
+                    }
+                }
+                "c" => {
+                    compositor.toggle_clamped();
+                }
+                "n" => {
+                    if mixer.supports_nearest() {
+                        compositor.toggle_nearest();
+                    } else {
+                        println!("nearest-neighbour scaling not supported by the mixer of the backend");
+                    }
+                }
+                "l" => {
+                    if loupe_enabled {
+                        compositor.toggle_loupe();
+                    } else {
+                        println!("loupe disabled, set `loupe = true` in the settings");
+                    }
+                }
+                "bracketleft" | "[" => {
+                    compositor.resize_inset(-32);
+                }
+                "bracketright" | "]" => {
+                    compositor.resize_inset(32);
+                }
+                "s" => {
+                    snapshot.lock().unwrap().save();
+                }
                 _ => (),
             },
+            NavigationEvent::KeyRelease { key, .. } => {
+                if key == "space" {
+                    compositor.hold_flicker(false);
+                }
+            }
             NavigationEvent::MouseMove { x, y, .. } => {
+                if compositor.loupe {
+                    compositor.move_loupe_to(x as i32, y as i32);
+                }
+
                 let state = state.lock().unwrap();
                 if state.clicked {
                     let new_xpos = (x - state.clicked_x) as i32 + state.clicked_xpos;
                     let new_ypos = (y - state.clicked_y) as i32 + state.clicked_ypos;
 
-                    compositor.move_pos_to(new_xpos, new_ypos);
+                    if state.clicked_inset {
+                        compositor.move_inset_to(new_xpos, new_ypos);
+                    } else {
+                        compositor.move_pos_to(new_xpos, new_ypos);
+                    }
                 }
             }
             NavigationEvent::MouseButtonPress { button, x, y, .. } => {
@@ -125,18 +226,41 @@ pub fn add_probe(
                     state.clicked = true;
                     state.clicked_x = x;
                     state.clicked_y = y;
+                    state.clicked_inset = compositor.is_inside_inset(x as i32, y as i32);
+                    if state.clicked_inset {
+                        state.clicked_xpos = compositor.inset_x;
+                        state.clicked_ypos = compositor.inset_y;
+                        return gst::PadProbeReturn::Ok;
+                    }
                     state.clicked_xpos = compositor.offset_x;
                     state.clicked_ypos = compositor.offset_y;
 
-                    if y >= 600.0 {
+                    // click in the bottom of the video (600 of 720) moves the border,
+                    // in the right of the video (1066 of 1280) in top_bottom mode,
+                    // the other modes have no border
+                    if compositor.is_top_bottom_mode() {
+                        if x >= (compositor.width * 5 / 6) as f64 {
+                            compositor.move_border_to(y as i32);
+                        }
+                    } else if compositor.is_split_mode()
+                        && y >= (compositor.height * 5 / 6) as f64
+                    {
                         compositor.move_border_to(x as i32);
                     }
                 } else if button == 2 || button == 3 || button == 274 || button == 273 {
                     compositor.reset();
                 } else if button == 4 {
-                    compositor.zoom_in_center_at(x as i32, y as i32);
+                    if compositor.loupe {
+                        compositor.loupe_zoom_in();
+                    } else {
+                        compositor.zoom_in_center_at(x as i32, y as i32);
+                    }
                 } else if button == 5 {
-                    compositor.zoom_out_center_at(x as i32, y as i32);
+                    if compositor.loupe {
+                        compositor.loupe_zoom_out();
+                    } else {
+                        compositor.zoom_out_center_at(x as i32, y as i32);
+                    }
                 }
             }
             NavigationEvent::MouseButtonRelease { button, .. } => {
@@ -146,26 +270,153 @@ pub fn add_probe(
                 }
             }
             NavigationEvent::MouseScroll { x, y, delta_y, .. } => {
+                // the scroll changes the loupe magnification while the loupe is shown
                 if delta_y > 0.0 {
-                    compositor.zoom_in_center_at(x as i32, y as i32);
+                    if compositor.loupe {
+                        compositor.loupe_zoom_in();
+                    } else {
+                        compositor.zoom_in_center_at(x as i32, y as i32);
+                    }
                 } else if delta_y < 0.0 {
-                    compositor.zoom_out_center_at(x as i32, y as i32);
+                    if compositor.loupe {
+                        compositor.loupe_zoom_out();
+                    } else {
+                        compositor.zoom_out_center_at(x as i32, y as i32);
+                    }
                 }
             }
             _ => (),
         }
 
         if original_compositor != *compositor {
-            pipeline::update_mixer(
-                &compositor,
-                &mixer_sink_0_pad,
-                &mixer_sink_1_pad,
-                &crop0,
-                &crop1,
-                compositor_supports_crop,
-            );
+            mixer.update(&compositor);
         }
 
         gst::PadProbeReturn::Ok
     });
 }
+
+/// Updates the size of the decoded video of each branch in the compositor, so the crops
+/// stay aligned when the branches have different resolutions
+fn add_caps_probes(
+    pipeline: &gst::Pipeline,
+    compositor: Arc<Mutex<Compositor>>,
+    mixer: pipeline::Mixer,
+) {
+    for index in 0..2 {
+        let crop_sink_pad = pipeline
+            .by_name(&format!("crop{index}"))
+            .unwrap()
+            .static_pad("sink")
+            .unwrap();
+        let compositor = compositor.clone();
+        let mixer = mixer.clone();
+
+        crop_sink_pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, probe_info| {
+            let Some(ev) = probe_info.event() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let gst::EventView::Caps(caps) = ev.view() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(info) = gst_video::VideoInfo::from_caps(caps.caps()) else {
+                return gst::PadProbeReturn::Ok;
+            };
+
+            let mut compositor = compositor.lock().unwrap();
+            compositor.set_branch_video_size(index, info.width() as i32, info.height() as i32);
+            mixer.update(&compositor);
+
+            gst::PadProbeReturn::Ok
+        });
+    }
+}
+
+/// Toggles the video shown in flicker mode every `flicker_interval` frames of the mixer
+fn add_flicker_probe(
+    mixer_src_pad: &gst::Pad,
+    compositor: Arc<Mutex<Compositor>>,
+    mixer: pipeline::Mixer,
+    settings: &Settings,
+) {
+    let flicker_interval = settings.flicker_interval as u64;
+    if flicker_interval == 0 {
+        return;
+    }
+
+    let num_buffers = AtomicU64::new(0);
+    mixer_src_pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+        let num_buffers = num_buffers.fetch_add(1, Ordering::Relaxed) + 1;
+        if !num_buffers.is_multiple_of(flicker_interval) {
+            return gst::PadProbeReturn::Ok;
+        }
+
+        let mut compositor = compositor.lock().unwrap();
+        if compositor.is_flicker_mode() {
+            compositor.flicker();
+            mixer.update(&compositor);
+        }
+
+        gst::PadProbeReturn::Ok
+    });
+}
+
+/// Splits a line typed in the terminal into key names. Known multi-char names are
+/// used as a key (as "Left"), otherwise each char is a key (as "++" is two zoom in).
+fn parse_keys(line: &str) -> Vec<String> {
+    const NAMES: [&str; 10] = [
+        "Left", "Right", "Up", "Down", "Shift_R", "space", "F1", "F2", "F3", "F4",
+    ];
+
+    let line = line.trim();
+    if NAMES.contains(&line) {
+        vec![line.to_string()]
+    } else {
+        line.chars().map(|c| c.to_string()).collect()
+    }
+}
+
+/// Reads keys from the terminal for the video sinks without key navigation events
+/// (glimagesink or gtk4paintablesink) and sends them as navigation events to the mixer,
+/// so they are handled by the probe added in `add_probe` as the ones from the video sink.
+pub fn add_stdin_keys(pipeline: &gst::Pipeline) {
+    let textoverlay = pipeline.by_name("metrics").unwrap();
+    let textoverlay_sink_pad = textoverlay.static_pad("video_sink").unwrap();
+
+    println!("Type the keys in the terminal followed by Enter");
+    std::thread::spawn(move || {
+        // keys can not be held in the terminal, space toggles the flicker hold
+        let mut space_held = false;
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            for key in parse_keys(&line) {
+                let event = if key == "space" && space_held {
+                    NavigationEvent::new_key_release(&key).build()
+                } else {
+                    NavigationEvent::new_key_press(&key).build()
+                };
+                if key == "space" {
+                    space_held = !space_held;
+                }
+                textoverlay_sink_pad.push_event(event);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(parse_keys("Left\n"), vec!["Left"]);
+        assert_eq!(parse_keys("++"), vec!["+", "+"]);
+        assert_eq!(parse_keys(" 4 "), vec!["4"]);
+        assert_eq!(parse_keys("F2"), vec!["F2"]);
+        assert!(parse_keys("").is_empty());
+    }
+}